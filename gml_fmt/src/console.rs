@@ -0,0 +1,81 @@
+use gml_fmt_lib::{render_error, Config, Error, Formatted, PrintFlags, Reporter};
+use std::path::Path;
+
+/// Reports to the terminal: problems always, and each file's input, output and
+/// syntax tree when `-l` and `-s` ask for them.
+pub struct Console<'a> {
+    config: &'a Config,
+    logs: bool,
+    ast: bool,
+}
+
+impl Console<'_> {
+    pub fn new(config: &Config) -> Console<'_> {
+        Console {
+            config,
+            logs: config.print_flags.contains(PrintFlags::LOGS),
+            ast: config.print_flags.contains(PrintFlags::LOG_AST),
+        }
+    }
+
+    /// The file's resource name in a GameMaker project, and its path otherwise.
+    fn describe(&self, path: &Path) -> String {
+        match self.config.resource_name(path) {
+            Some(name) => name.to_owned(),
+            None => format!("{:?}", path),
+        }
+    }
+}
+
+impl Reporter for Console<'_> {
+    fn on_file_start(&mut self, _path: &Path, source: &str) {
+        if self.logs {
+            println!("=========INPUT=========");
+            println!("{}", source);
+        }
+    }
+
+    fn on_formatted(&mut self, path: &Path, source: &str, formatted: &Formatted, ast: Option<&str>) {
+        if formatted.diagnostics.is_empty() == false {
+            println!(
+                "Could not parse all of {}. The parts we couldn't parse were left as they were.",
+                self.describe(path)
+            );
+            for diagnostic in &formatted.diagnostics {
+                print!("{}", diagnostic.render(source, Some(path)));
+            }
+        }
+
+        if self.logs {
+            println!("=========OUTPUT=========");
+            println!("{}", formatted.output);
+        }
+
+        if let (true, Some(ast)) = (self.ast, ast) {
+            println!("==========AST===========");
+            println!("{}", ast);
+        }
+    }
+
+    fn on_error(&mut self, path: &Path, source: Option<&str>, error: &Error) {
+        match (error, source) {
+            (Error::Parse { .. }, Some(source)) => {
+                println!("Could not parse {}", self.describe(path));
+                print!("{}", render_error(error, source, Some(path)));
+            }
+            (Error::Verification { diagnostic, .. }, Some(source)) => {
+                println!(
+                    "Formatting {} changed more than whitespace, so it was left as it was.",
+                    self.describe(path)
+                );
+                print!("{}", diagnostic.render(source, Some(path)));
+            }
+            (Error::Unstable { diff, .. }, _) => {
+                println!("Formatting {} a second time changed it again:", self.describe(path));
+                print!("{}", diff);
+            }
+            // Files we couldn't read or write are listed when the run ends.
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,124 @@
+use gml_fmt_lib::ast::{Ast, Scanner, Token};
+use gml_fmt_lib::{is_gmx_file, Config, Diagnostic};
+use serde::Serialize;
+use std::fs;
+use std::io;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+/// How one file parsed: its `ast`, or the `diagnostics` explaining why it couldn't be parsed.
+#[derive(Serialize)]
+struct ParsedFile<'a> {
+    path: &'a Path,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ast: Option<&'a Ast<'a>>,
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    diagnostics: &'a [Diagnostic],
+}
+
+/// Prints the syntax tree of every file in `config` as one JSON array.
+pub fn emit_ast_json(config: &Config) -> io::Result<()> {
+    let files = gml_files(config);
+    let contents = files.iter().map(fs::read_to_string).collect::<io::Result<Vec<_>>>()?;
+
+    let results: Vec<_> = contents.iter().map(|contents| gml_fmt_lib::parse(contents)).collect();
+    let parsed: Vec<_> = files
+        .iter()
+        .zip(&results)
+        .map(|(this_file, result)| match result {
+            Ok(ast) => ParsedFile {
+                path: this_file,
+                ast: Some(ast),
+                diagnostics: &[],
+            },
+            Err(diagnostics) => ParsedFile {
+                path: this_file,
+                ast: None,
+                diagnostics,
+            },
+        })
+        .collect();
+
+    println!("{}", serde_json::to_string_pretty(&parsed)?);
+    Ok(())
+}
+
+/// A token as we print it. Lines and columns start at 1, like in diagnostics.
+#[derive(Serialize)]
+struct TokenEntry<'a> {
+    #[serde(rename = "type")]
+    kind: &'static str,
+    text: &'a str,
+    line: u32,
+    column: u32,
+    span: Range<u32>,
+}
+
+#[derive(Serialize)]
+struct ScannedFile<'a> {
+    path: &'a Path,
+    tokens: Vec<TokenEntry<'a>>,
+}
+
+impl<'a> TokenEntry<'a> {
+    fn new(source: &'a str, token: Token<'a>) -> TokenEntry<'a> {
+        TokenEntry {
+            kind: token.token_type.name(),
+            text: &source[token.span.start as usize..token.span.end as usize],
+            line: token.line_number + 1,
+            column: token.column_number + 1,
+            span: token.span,
+        }
+    }
+}
+
+/// Prints every token `Scanner` finds in each file in `config`, one per line as
+/// `LINE:COLUMN START..END TYPE "TEXT"`, under a `==> PATH <==` header per file.
+/// With `json`, prints one JSON array with an entry per file instead.
+pub fn emit_tokens(config: &Config, json: bool) -> io::Result<()> {
+    let files = gml_files(config);
+    let contents = files.iter().map(fs::read_to_string).collect::<io::Result<Vec<_>>>()?;
+
+    let scanned: Vec<_> = files
+        .iter()
+        .zip(&contents)
+        .map(|(this_file, source)| ScannedFile {
+            path: this_file,
+            tokens: Scanner::new(source)
+                .map(|token| TokenEntry::new(source, token))
+                .collect(),
+        })
+        .collect();
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&scanned)?);
+        return Ok(());
+    }
+
+    for file in scanned {
+        println!("==> {} <==", file.path.display());
+        for token in file.tokens {
+            println!(
+                "{}:{} {}..{} {} \"{}\"",
+                token.line,
+                token.column,
+                token.span.start,
+                token.span.end,
+                token.kind,
+                token.text.escape_debug()
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// The files in `config` which are GML through and through. The code in a `.gmx`
+/// file is spread through XML, so there's no one source to show.
+fn gml_files(config: &Config) -> Vec<&PathBuf> {
+    config
+        .files
+        .iter()
+        .filter(|this_file| is_gmx_file(this_file) == false)
+        .collect()
+}
@@ -0,0 +1,401 @@
+use gml_fmt_lib::ast::{Scanner, TokenType};
+use gml_fmt_lib::{verify_tokens, Diagnostic, LangConfig, LineIndex, Severity};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::io::{self, BufRead, BufReader, Write};
+use std::ops::RangeInclusive;
+use std::path::PathBuf;
+
+/// JSON-RPC's code for a message which isn't JSON.
+const PARSE_ERROR: i64 = -32700;
+/// JSON-RPC's code for a method we don't implement.
+const METHOD_NOT_FOUND: i64 = -32601;
+/// LSP's code for a request sent before `initialize`.
+const SERVER_NOT_INITIALIZED: i64 = -32002;
+
+/// Speaks the Language Server Protocol over stdin and stdout until the client
+/// sends `exit`. We format documents as the editor has them, saved or not.
+pub fn serve() -> anyhow::Result<()> {
+    let stdin = io::stdin();
+    let mut input = BufReader::new(stdin.lock());
+    let stdout = io::stdout();
+    let mut output = stdout.lock();
+
+    let mut server = Server::default();
+    while let Some(content) = read_message(&mut input)? {
+        // We can't tell which request a broken message was, but the client can still
+        // send good ones after it, so we answer with a null id and keep going.
+        let message: Value = match serde_json::from_slice(&content) {
+            Ok(message) => message,
+            Err(e) => {
+                eprintln!("Ignoring a message which isn't JSON: {}", e);
+                let error = json!({
+                    "jsonrpc": "2.0",
+                    "id": null,
+                    "error": { "code": PARSE_ERROR, "message": format!("parse error: {}", e) },
+                });
+                write_message(&mut output, &error)?;
+                continue;
+            }
+        };
+
+        if server.handle(&message, &mut output)? == false {
+            break;
+        }
+    }
+
+    if server.shut_down == false {
+        anyhow::bail!("the client exited without asking the server to shut down");
+    }
+    Ok(())
+}
+
+#[derive(Default)]
+struct Server {
+    lang_config: Option<LangConfig>,
+    documents: HashMap<String, String>,
+    shut_down: bool,
+}
+
+impl Server {
+    /// Handles one message, and gives back whether to keep going.
+    fn handle(&mut self, message: &Value, output: &mut impl Write) -> io::Result<bool> {
+        let method = message["method"].as_str().unwrap_or_default();
+        let params = &message["params"];
+
+        // Only requests have ids, and every request needs a response.
+        let id = match message.get("id") {
+            Some(id) => id,
+            None => {
+                if method == "exit" {
+                    return Ok(false);
+                }
+                self.notification(method, params, output)?;
+                return Ok(true);
+            }
+        };
+
+        if method == "initialize" {
+            let result = self.initialize(params);
+            write_message(output, &json!({ "jsonrpc": "2.0", "id": id, "result": result }))?;
+            return Ok(true);
+        }
+
+        let result = match (method, &self.lang_config) {
+            (_, None) => Err((SERVER_NOT_INITIALIZED, "the server hasn't been initialized")),
+            ("shutdown", _) => {
+                self.shut_down = true;
+                Ok(Value::Null)
+            }
+            ("textDocument/formatting", Some(lang_config)) => {
+                Ok(self.edits(params, |source| format_document(source, lang_config)))
+            }
+            ("textDocument/rangeFormatting", Some(lang_config)) => {
+                let lines = selected_lines(&params["range"]);
+                Ok(self.edits(params, |source| format_lines(source, lang_config, lines)))
+            }
+            ("textDocument/onTypeFormatting", Some(lang_config)) => {
+                let line = line_number(&params["position"]);
+                Ok(self.edits(params, |source| {
+                    // A `}` finishes a block, so we format it all rather than just its last line.
+                    let lines = match params["ch"].as_str() {
+                        Some("}") => closed_block(source, &params["position"]).unwrap_or(line..=line),
+                        _ => line..=line,
+                    };
+                    format_lines(source, lang_config, lines)
+                }))
+            }
+            _ => Err((METHOD_NOT_FOUND, "unknown method")),
+        };
+
+        let response = match result {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err((code, message)) => json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "error": { "code": code, "message": format!("{}: {}", message, method) },
+            }),
+        };
+        write_message(output, &response)?;
+        Ok(true)
+    }
+
+    fn initialize(&mut self, params: &Value) -> Value {
+        // We read gml_fmt.toml from the workspace, just as the CLI reads it from where it runs.
+        let root = params["rootUri"]
+            .as_str()
+            .and_then(uri_to_path)
+            .or_else(|| params["rootPath"].as_str().map(PathBuf::from))
+            .filter(|root| root.is_dir());
+        let lang_config = root.and_then(|root| LangConfig::load(&root).ok());
+        self.lang_config = Some(lang_config.unwrap_or_default());
+
+        json!({
+            "capabilities": {
+                "textDocumentSync": 1,
+                "documentFormattingProvider": true,
+                "documentRangeFormattingProvider": true,
+                "documentOnTypeFormattingProvider": {
+                    "firstTriggerCharacter": "}",
+                    "moreTriggerCharacter": [";"],
+                },
+            },
+            "serverInfo": { "name": "gml_fmt", "version": env!("CARGO_PKG_VERSION") },
+        })
+    }
+
+    fn notification(&mut self, method: &str, params: &Value, output: &mut impl Write) -> io::Result<()> {
+        let uri = params["textDocument"]["uri"].as_str().unwrap_or_default();
+
+        match method {
+            "textDocument/didOpen" => {
+                let text = params["textDocument"]["text"].as_str().unwrap_or_default();
+                self.documents.insert(uri.to_owned(), text.to_owned());
+            }
+            // We ask for full syncs, so the last change holds the whole document.
+            "textDocument/didChange" => match params["contentChanges"].as_array().and_then(|c| c.last()) {
+                Some(change) => {
+                    let text = change["text"].as_str().unwrap_or_default();
+                    self.documents.insert(uri.to_owned(), text.to_owned());
+                }
+                None => return Ok(()),
+            },
+            "textDocument/didClose" => {
+                self.documents.remove(uri);
+                return publish_diagnostics(output, uri, &[]);
+            }
+            _ => return Ok(()),
+        }
+
+        let diagnostics = match gml_fmt_lib::parse(&self.documents[uri]) {
+            Ok(_) => Vec::new(),
+            Err(diagnostics) => diagnostics
+                .iter()
+                .map(|diagnostic| lsp_diagnostic(&self.documents[uri], diagnostic))
+                .collect(),
+        };
+        publish_diagnostics(output, uri, &diagnostics)
+    }
+
+    /// The edits turning the document into what `format` makes of it. Documents we
+    /// haven't been sent, or which ask to be ignored, are left alone.
+    fn edits(&self, params: &Value, format: impl FnOnce(&str) -> Option<String>) -> Value {
+        let uri = params["textDocument"]["uri"].as_str().unwrap_or_default();
+        let source = match self.documents.get(uri) {
+            Some(source) if source.contains("// @gml_fmt ignore") == false => source,
+            _ => return json!([]),
+        };
+
+        match format(source).and_then(|output| text_edit(source, &output)) {
+            Some(edit) => json!([edit]),
+            None => json!([]),
+        }
+    }
+}
+
+/// Formats what parses and leaves the rest. Like the CLI, we never give back a
+/// format which changed more than whitespace.
+fn format_document(source: &str, lang_config: &LangConfig) -> Option<String> {
+    let formatted = gml_fmt_lib::run_recovering(source, lang_config, None);
+    verify_tokens(source, &formatted.output).ok()?;
+    Some(formatted.output)
+}
+
+fn format_lines(source: &str, lang_config: &LangConfig, lines: RangeInclusive<u32>) -> Option<String> {
+    let output = gml_fmt_lib::run_lines(source, lang_config, &[lines]).ok()?;
+    verify_tokens(source, &output).ok()?;
+    Some(output)
+}
+
+/// One edit replacing only the lines which changed, so the editor keeps the
+/// cursor, folds and undo history everywhere else.
+fn text_edit(source: &str, output: &str) -> Option<Value> {
+    if source == output {
+        return None;
+    }
+
+    let old_lines: Vec<_> = source.split_inclusive('\n').collect();
+    let new_lines: Vec<_> = output.split_inclusive('\n').collect();
+
+    let prefix = old_lines.iter().zip(&new_lines).take_while(|(a, b)| a == b).count();
+    let max_suffix = old_lines.len().min(new_lines.len()) - prefix;
+    let suffix = old_lines
+        .iter()
+        .rev()
+        .zip(new_lines.iter().rev())
+        .take(max_suffix)
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    let start: usize = old_lines[..prefix].iter().map(|line| line.len()).sum();
+    let end = source.len()
+        - old_lines[old_lines.len() - suffix..]
+            .iter()
+            .map(|line| line.len())
+            .sum::<usize>();
+    let line_index = LineIndex::new(source);
+
+    Some(json!({
+        "range": {
+            "start": position(source, &line_index, start as u32),
+            "end": position(source, &line_index, end as u32),
+        },
+        "newText": new_lines[prefix..new_lines.len() - suffix].concat(),
+    }))
+}
+
+fn lsp_diagnostic(source: &str, diagnostic: &Diagnostic) -> Value {
+    let line_index = LineIndex::new(source);
+    let message = match &diagnostic.help {
+        Some(help) => format!("{}\nhelp: {}", diagnostic.message, help),
+        None => diagnostic.message.clone(),
+    };
+
+    json!({
+        "range": {
+            "start": position(source, &line_index, diagnostic.span.start),
+            "end": position(source, &line_index, diagnostic.span.end),
+        },
+        "severity": match diagnostic.severity {
+            Severity::Error => 1,
+            Severity::Warning => 2,
+        },
+        "source": "gml_fmt",
+        "message": message,
+    })
+}
+
+fn publish_diagnostics(output: &mut impl Write, uri: &str, diagnostics: &[Value]) -> io::Result<()> {
+    write_message(
+        output,
+        &json!({
+            "jsonrpc": "2.0",
+            "method": "textDocument/publishDiagnostics",
+            "params": { "uri": uri, "diagnostics": diagnostics },
+        }),
+    )
+}
+
+/// LSP counts characters in UTF-16 code units, where we count bytes.
+fn position(source: &str, line_index: &LineIndex, offset: u32) -> Value {
+    let line = line_index.line(offset);
+    let line_start = line_index.line_start(line) as usize;
+    let character = source[line_start..offset as usize].encode_utf16().count();
+
+    json!({ "line": line, "character": character })
+}
+
+/// The byte offset of an LSP position, the other way around from `position`.
+fn offset(source: &str, line_index: &LineIndex, position: &Value) -> usize {
+    let line_start = line_index.line_start(line_number(position)) as usize;
+    let character = position["character"].as_u64().unwrap_or_default() as usize;
+
+    let mut units = 0;
+    for (offset, c) in source[line_start..].char_indices() {
+        if units >= character || c == '\n' {
+            return line_start + offset;
+        }
+        units += c.len_utf16();
+    }
+    source.len()
+}
+
+/// The lines a selection covers. A selection ending at the very start of a line
+/// doesn't take in anything on it, so that line is left out.
+fn selected_lines(range: &Value) -> RangeInclusive<u32> {
+    let start = line_number(&range["start"]);
+    let mut end = line_number(&range["end"]);
+    if end > start && range["end"]["character"].as_u64() == Some(0) {
+        end -= 1;
+    }
+
+    start..=end
+}
+
+/// The lines from the `{` matching the last `}` before `position` down to that `}`.
+fn closed_block(source: &str, position: &Value) -> Option<RangeInclusive<u32>> {
+    let cursor = offset(source, &LineIndex::new(source), position);
+    let tokens: Vec<_> = Scanner::new(source)
+        .take_while(|token| token.span.end as usize <= cursor)
+        .collect();
+    let close = tokens
+        .iter()
+        .rposition(|token| token.token_type == TokenType::RightBrace)?;
+
+    let mut depth = 0;
+    for token in tokens[..=close].iter().rev() {
+        match token.token_type {
+            TokenType::RightBrace => depth += 1,
+            TokenType::LeftBrace if depth == 1 => return Some(token.line_number..=tokens[close].line_number),
+            TokenType::LeftBrace => depth -= 1,
+            _ => {}
+        }
+    }
+
+    None
+}
+
+fn line_number(position: &Value) -> u32 {
+    position["line"].as_u64().unwrap_or_default() as u32
+}
+
+/// Turns a `file://` URI into a path, undoing its percent-encoding.
+fn uri_to_path(uri: &str) -> Option<PathBuf> {
+    let path = uri.strip_prefix("file://")?;
+    let mut bytes = Vec::with_capacity(path.len());
+    let mut rest = path.as_bytes();
+
+    while let Some((&byte, tail)) = rest.split_first() {
+        match (byte, tail.get(..2)) {
+            (b'%', Some(hex)) => {
+                let hex = std::str::from_utf8(hex).ok()?;
+                bytes.push(u8::from_str_radix(hex, 16).ok()?);
+                rest = &tail[2..];
+            }
+            _ => {
+                bytes.push(byte);
+                rest = tail;
+            }
+        }
+    }
+
+    let path = String::from_utf8(bytes).ok()?;
+    // `file:///C:/project` on Windows.
+    match path.strip_prefix('/') {
+        Some(windows) if windows.get(1..2) == Some(":") => Some(PathBuf::from(windows)),
+        _ => Some(PathBuf::from(path)),
+    }
+}
+
+/// Reads the content of one message, framed by a `Content-Length` header. `None` means stdin closed.
+fn read_message(input: &mut impl BufRead) -> io::Result<Option<Vec<u8>>> {
+    let mut content_length = None;
+
+    loop {
+        let mut header = String::new();
+        if input.read_line(&mut header)? == 0 {
+            return Ok(None);
+        }
+
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some(length) = header.strip_prefix("Content-Length:") {
+            content_length = length.trim().parse::<usize>().ok();
+        }
+    }
+
+    let content_length = content_length
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "message had no Content-Length header"))?;
+    let mut content = vec![0; content_length];
+    input.read_exact(&mut content)?;
+
+    Ok(Some(content))
+}
+
+fn write_message(output: &mut impl Write, message: &Value) -> io::Result<()> {
+    let content = message.to_string();
+    write!(output, "Content-Length: {}\r\n\r\n{}", content.len(), content)?;
+    output.flush()
+}
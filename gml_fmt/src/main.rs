@@ -1,10 +1,29 @@
-use clap::{App, Arg};
-use gml_fmt_lib::{Config, LangConfig, PrintFlags};
-use std::{path::PathBuf, process};
+#![allow(clippy::bool_comparison)]
 
-const VERSION: &'static str = env!("CARGO_PKG_VERSION");
+use anyhow::Context;
+use clap::{App, Arg, SubCommand};
+use console::Console;
+use gml_fmt_lib::{
+    Backup, Config, FileFilters, GitChanges, LangConfig, PrintFlags, ResourceKind, Silent, CACHE_FILE_NAME,
+};
+use std::time::{SystemTime, UNIX_EPOCH};
+use std::{ops::RangeInclusive, path::PathBuf, process};
+
+mod console;
+mod emit;
+mod lsp;
+mod watch;
+
+const VERSION: &str = env!("CARGO_PKG_VERSION");
 
 fn main() {
+    if let Err(err) = run() {
+        eprintln!("Error: {:#}", err);
+        process::exit(1);
+    }
+}
+
+fn run() -> anyhow::Result<()> {
     let matches = App::new("gml_fmt")
         .version(VERSION)
         .version_short("v")
@@ -31,11 +50,151 @@ fn main() {
                 .short("n")
                 .help("Do not overwrite the original file. Mostly used in conjungtion with -l to log output."),
         )
+        .arg(
+            Arg::with_name("watch")
+                .short("w")
+                .long("watch")
+                .help("Watches the path and formats each .gml file when it is saved"),
+        )
+        .arg(
+            Arg::with_name("jobs")
+                .short("j")
+                .long("jobs")
+                .takes_value(true)
+                .value_name("N")
+                .help("Formats N files at once. Defaults to the number of cores"),
+        )
+        .arg(
+            Arg::with_name("include")
+                .long("include")
+                .takes_value(true)
+                .value_name("GLOB")
+                .multiple(true)
+                .number_of_values(1)
+                .help("Only formats files matching this glob. Can be given more than once"),
+        )
+        .arg(
+            Arg::with_name("exclude")
+                .long("exclude")
+                .takes_value(true)
+                .value_name("GLOB")
+                .multiple(true)
+                .number_of_values(1)
+                .help("Skips files and directories matching this glob. Can be given more than once"),
+        )
+        .arg(
+            Arg::with_name("only")
+                .long("only")
+                .takes_value(true)
+                .value_name("KIND")
+                .possible_values(&["scripts", "objects", "rooms", "timelines"])
+                .multiple(true)
+                .number_of_values(1)
+                .help("In a project with a .yyp, only formats this kind of resource. Can be given more than once"),
+        )
+        .arg(
+            Arg::with_name("follow-symlinks")
+                .long("follow-symlinks")
+                .help("Walks into symlinked directories"),
+        )
+        .arg(
+            Arg::with_name("respect-gitignore")
+                .long("respect-gitignore")
+                .help("Skips files ignored by the project's .gitignore"),
+        )
+        .arg(
+            Arg::with_name("changed")
+                .long("changed")
+                .conflicts_with_all(&["since", "staged", "watch"])
+                .help("Only formats files git reports as modified, staged or untracked"),
+        )
+        .arg(
+            Arg::with_name("since")
+                .long("since")
+                .takes_value(true)
+                .value_name("REV")
+                .conflicts_with_all(&["staged", "watch"])
+                .help("Only formats files changed since the git revision REV, or untracked"),
+        )
+        .arg(
+            Arg::with_name("staged")
+                .long("staged")
+                .conflicts_with("watch")
+                .help("Formats the staged content of staged files and stages the result. Meant for pre-commit hooks"),
+        )
+        .arg(
+            Arg::with_name("lines")
+                .long("lines")
+                .takes_value(true)
+                .value_name("START:END")
+                .multiple(true)
+                .number_of_values(1)
+                .conflicts_with_all(&["watch", "staged"])
+                .help(
+                    "Only formats the statements on lines START to END, counting from 1. Can be given more than once",
+                ),
+        )
+        .arg(
+            Arg::with_name("backup")
+                .long("backup")
+                .conflicts_with("backup-dir")
+                .help("Keeps a copy of each file we change next to it, as file.gml.orig"),
+        )
+        .arg(
+            Arg::with_name("backup-dir")
+                .long("backup-dir")
+                .takes_value(true)
+                .value_name("DIR")
+                .help("Keeps a copy of each file we change in a timestamped folder inside DIR"),
+        )
+        .arg(
+            Arg::with_name("report")
+                .long("report")
+                .takes_value(true)
+                .value_name("FORMAT")
+                .possible_values(&["json"])
+                .conflicts_with_all(&["watch", "staged"])
+                .help("Prints what happened to each file as FORMAT instead of logging"),
+        )
+        .arg(
+            Arg::with_name("no-cache")
+                .long("no-cache")
+                .help("Formats every file, even ones the cache says are already formatted"),
+        )
+        .arg(
+            Arg::with_name("no-verify")
+                .long("no-verify")
+                .help("Writes formatted files without checking that only whitespace changed"),
+        )
+        .arg(
+            Arg::with_name("verify-idempotent")
+                .long("verify-idempotent")
+                .conflicts_with_all(&["watch", "staged", "report", "lines"])
+                .help("Formats each file twice without writing, and shows any file the second pass changes"),
+        )
+        .arg(
+            Arg::with_name("emit")
+                .long("emit")
+                .takes_value(true)
+                .value_name("FORMAT")
+                .possible_values(&["ast-json", "tokens", "tokens-json"])
+                .conflicts_with_all(&["watch", "staged", "report", "lines", "verify-idempotent"])
+                .help("Prints how each file scans or parses as FORMAT instead of formatting it"),
+        )
+        .subcommand(
+            SubCommand::with_name("lsp")
+                .about("Runs a language server over stdin and stdout, for editors to format with"),
+        )
         .get_matches();
 
+    if matches.subcommand_matches("lsp").is_some() {
+        return lsp::serve();
+    }
+
     // Get our path and make our lang_config file
     let our_path = std::env::current_dir().unwrap();
-    let lang_config = LangConfig::new(&our_path);
+    let lang_config = LangConfig::load(&our_path)?;
+    let mut filters = FileFilters::load(&our_path)?;
 
     // Get Path
     let input_path = if matches.is_present("PATH") {
@@ -66,18 +225,141 @@ fn main() {
         print_flags.insert(PrintFlags::LOG_AST);
     }
 
-    let config = Config::new(input_path, print_flags, do_file).unwrap_or_else(|e| {
-        eprintln!("File reading error: {}", e);
-        process::exit(1);
-    });
+    // Which files do we take in?
+    if let Some(globs) = matches.values_of("include") {
+        filters.include.extend(globs.map(String::from));
+    }
+
+    if let Some(globs) = matches.values_of("exclude") {
+        filters.exclude.extend(globs.map(String::from));
+    }
+
+    if let Some(kinds) = matches.values_of("only") {
+        filters.only.extend(kinds.filter_map(ResourceKind::from_plural));
+    }
 
-    match gml_fmt_lib::run_with_config(&config, &lang_config) {
-        Ok(()) => {
-            println!("Format complete.");
+    if matches.is_present("respect-gitignore") {
+        filters.respect_gitignore = true;
+    }
+
+    if matches.is_present("follow-symlinks") {
+        filters.follow_symlinks = true;
+    }
+
+    let mut config =
+        Config::with_filters(input_path.clone(), print_flags, do_file, filters).context("File reading error")?;
+
+    for e in &config.traversal_errors {
+        eprintln!("Skipping: {}", e);
+    }
+
+    if let Some(jobs) = matches.value_of("jobs") {
+        config.jobs = jobs
+            .parse()
+            .with_context(|| format!("--jobs expects a number, but got {}", jobs))?;
+    }
+
+    if matches.is_present("backup") {
+        config.backup = Backup::Orig;
+    } else if let Some(dir) = matches.value_of("backup-dir") {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |since_epoch| since_epoch.as_secs());
+        config.backup = Backup::Directory {
+            dir: PathBuf::from(dir).join(format!("gml_fmt_backup_{}", timestamp)),
+            root: input_path.clone(),
+        };
+    }
+
+    if matches.is_present("no-verify") {
+        config.verify = false;
+    }
+
+    if matches.is_present("no-cache") == false {
+        let cache_dir = if input_path.is_dir() {
+            input_path.clone()
+        } else {
+            match input_path.parent() {
+                Some(parent) if parent.as_os_str().is_empty() == false => parent.to_path_buf(),
+                _ => PathBuf::from("."),
+            }
+        };
+        config.cache = Some(cache_dir.join(CACHE_FILE_NAME));
+    }
+
+    if let Some(ranges) = matches.values_of("lines") {
+        for range in ranges {
+            match parse_line_range(range) {
+                Some(range) => config.lines.push(range),
+                None => anyhow::bail!("--lines expects START:END, such as 10:40, but got {}", range),
+            }
         }
-        Err(err) => {
-            eprintln!("Error: {}", err);
+    }
+
+    let changes = if matches.is_present("staged") {
+        Some(GitChanges::Staged)
+    } else if let Some(rev) = matches.value_of("since") {
+        Some(GitChanges::Since(rev.to_owned()))
+    } else if matches.is_present("changed") {
+        Some(GitChanges::Uncommitted)
+    } else {
+        None
+    };
+
+    if let Some(changes) = &changes {
+        config.retain_changed(changes)?;
+    }
+
+    if changes == Some(GitChanges::Staged) {
+        gml_fmt_lib::format_staged(&config, &lang_config, &mut Console::new(&config))?;
+        println!("Format complete.");
+        return Ok(());
+    }
+
+    if let Some(emit) = matches.value_of("emit") {
+        match emit {
+            "ast-json" => emit::emit_ast_json(&config)?,
+            "tokens" => emit::emit_tokens(&config, false)?,
+            _ => emit::emit_tokens(&config, true)?,
+        };
+        return Ok(());
+    }
+
+    if matches.is_present("verify-idempotent") {
+        gml_fmt_lib::verify_idempotent(&config, &lang_config, &mut Console::new(&config))?;
+        println!("Every file is stable.");
+        return Ok(());
+    }
+
+    if matches.is_present("watch") {
+        watch::watch(&input_path, config, &lang_config)?;
+        return Ok(());
+    }
+
+    if matches.value_of("report") == Some("json") {
+        let report = gml_fmt_lib::format_files(&config, &lang_config, &mut Silent);
+        println!("{}", serde_json::to_string_pretty(&report)?);
+
+        if report.totals.failed > 0 || report.totals.verification_failures > 0 {
             process::exit(1);
         }
-    };
+        return Ok(());
+    }
+
+    gml_fmt_lib::run_with_config(&config, &lang_config, &mut Console::new(&config))?;
+    println!("Format complete.");
+    Ok(())
+}
+
+/// Turns the 1-based `START:END` the user gives us into the 0-based range the library uses.
+fn parse_line_range(range: &str) -> Option<RangeInclusive<u32>> {
+    let (start, end) = range.split_once(':')?;
+    let start: u32 = start.trim().parse().ok()?;
+    let end: u32 = end.trim().parse().ok()?;
+
+    if start == 0 || end < start {
+        return None;
+    }
+
+    Some(start - 1..=end - 1)
 }
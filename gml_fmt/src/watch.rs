@@ -0,0 +1,110 @@
+use crate::console::Console;
+use gml_fmt_lib::{Config, FileStatus, LangConfig, PrintFlags};
+use notify::{DebouncedEvent, RecursiveMode, Watcher};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::channel;
+use std::time::Duration;
+
+/// How long the watcher waits for a burst of writes to settle before formatting.
+const DEBOUNCE_MS: u64 = 250;
+
+/// Watches `input_path` and reformats `.gml` and `.gmx` files as they are saved. Runs until the
+/// watcher itself fails; parse errors in a file are logged, and the rest of the file is still formatted.
+pub fn watch(input_path: &Path, mut config: Config, lang_config: &LangConfig) -> notify::Result<()> {
+    let (tx, rx) = channel();
+    let mut watcher = notify::watcher(tx, Duration::from_millis(DEBOUNCE_MS))?;
+
+    // When we're watching a single file, we watch its directory instead, since
+    // most editors save by renaming a temporary file over the original.
+    if input_path.is_dir() {
+        watcher.watch(input_path, RecursiveMode::Recursive)?;
+    } else {
+        let parent = match input_path.parent() {
+            Some(parent) if parent.as_os_str().is_empty() == false => parent.to_path_buf(),
+            _ => PathBuf::from("."),
+        };
+        watcher.watch(&parent, RecursiveMode::NonRecursive)?;
+    }
+
+    println!("Watching {:?} for changes...", input_path);
+    let overwrite = config.print_flags.contains(PrintFlags::OVERWRITE);
+
+    // Hashes of the content we last wrote, so we don't reformat our own writes.
+    let mut written_hashes: HashMap<PathBuf, u64> = HashMap::new();
+    // main has already told the user what the first walk skipped.
+    let mut reported_skips: HashSet<String> = config.traversal_errors.iter().map(|e| e.to_string()).collect();
+
+    loop {
+        let event = match rx.recv() {
+            Ok(event) => event,
+            Err(_) => return Ok(()),
+        };
+
+        let changed = match event {
+            DebouncedEvent::Write(path) | DebouncedEvent::Create(path) | DebouncedEvent::Rename(_, path) => path,
+            DebouncedEvent::Error(e, _) => return Err(e),
+            _ => continue,
+        };
+
+        if Config::is_gml_file(&changed) == false || changed.is_file() == false {
+            continue;
+        }
+
+        // notify has its own spelling of the path, and the file might be new, so we ask
+        // the config whether it's one of ours and what it calls it.
+        let this_file = match config.take_in_file(&changed) {
+            Ok(Some(this_file)) => this_file,
+            Ok(None) => continue,
+            Err(e) => {
+                println!("Could not read {:?}: {}", input_path, e);
+                continue;
+            }
+        };
+
+        for e in &config.traversal_errors {
+            if reported_skips.insert(e.to_string()) {
+                eprintln!("Skipping: {}", e);
+            }
+        }
+
+        if let Ok(contents) = fs::read_to_string(&this_file) {
+            if written_hashes.get(&this_file) == Some(&hash_of(&contents)) {
+                continue;
+            }
+        }
+
+        let report = gml_fmt_lib::format_single_file(&this_file, &config, lang_config, &mut Console::new(&config));
+        match report.status {
+            FileStatus::Reformatted | FileStatus::FormattedWithErrors if overwrite => {
+                if let Ok(output) = fs::read_to_string(&this_file) {
+                    written_hashes.insert(this_file.clone(), hash_of(&output));
+                }
+                println!("Formatted {}", describe(&this_file, &config));
+            }
+            FileStatus::Failed => {
+                println!("Could not format {}", describe(&this_file, &config));
+                if let Some(error) = &report.error {
+                    println!("{}", error.message);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn hash_of(contents: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    contents.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// The file's resource name in a GameMaker project, and its path otherwise.
+fn describe(this_file: &Path, config: &Config) -> String {
+    config
+        .resource_name(this_file)
+        .map_or_else(|| format!("{:?}", this_file), String::from)
+}
@@ -0,0 +1,110 @@
+use serde_json::{json, Value};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// A folder with a file that parses and one that doesn't, removed when the test ends.
+struct Project(PathBuf);
+
+impl Project {
+    fn new(name: &str) -> Project {
+        let path = std::env::temp_dir().join(format!("gml_fmt_emit_{}_{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&path);
+        std::fs::create_dir_all(&path).unwrap();
+        std::fs::write(path.join("a.gml"), "var x = \"a\";\n").unwrap();
+        std::fs::write(path.join("b.gml"), "switch (a) { x = 2; }\n").unwrap();
+        Project(path)
+    }
+}
+
+impl Drop for Project {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+fn emit(path: &Path, kind: &str) -> String {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_gml_fmt"));
+    if path.is_file() {
+        command.arg("-f");
+    }
+    let output = command
+        .arg(path)
+        .args(["--emit", kind])
+        .output()
+        .expect("Could not start gml_fmt");
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    String::from_utf8(output.stdout).unwrap()
+}
+
+/// The entry for the file named `name`, since walking doesn't promise an order.
+fn entry<'a>(files: &'a Value, name: &str) -> &'a Value {
+    files
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|file| file["path"].as_str().unwrap().ends_with(name))
+        .unwrap_or_else(|| panic!("{} is missing from {}", name, files))
+}
+
+#[test]
+fn emit_tokens_lists_each_token_with_its_position() {
+    let project = Project::new("tokens");
+    let output = emit(&project.0.join("a.gml"), "tokens");
+
+    let lines: Vec<_> = output.lines().collect();
+    assert_eq!(
+        lines,
+        [
+            format!("==> {} <==", project.0.join("a.gml").display()).as_str(),
+            "1:1 0..3 Var \"var\"",
+            "1:5 4..5 Identifier \"x\"",
+            "1:7 6..7 Equal \"=\"",
+            "1:9 8..11 String \"\\\"a\\\"\"",
+            "1:12 11..12 Semicolon \";\"",
+            "1:13 12..13 Newline \"\\n\"",
+        ]
+    );
+}
+
+#[test]
+fn emit_tokens_json_gives_every_file_its_tokens() {
+    let project = Project::new("tokens_json");
+    let files: Value = serde_json::from_str(&emit(&project.0, "tokens-json")).unwrap();
+
+    assert_eq!(files.as_array().unwrap().len(), 2);
+    let tokens = entry(&files, "a.gml")["tokens"].as_array().unwrap();
+    let types: Vec<_> = tokens.iter().map(|token| token["type"].as_str().unwrap()).collect();
+    assert_eq!(types, ["Var", "Identifier", "Equal", "String", "Semicolon", "Newline"]);
+    assert_eq!(
+        tokens[3],
+        json!({ "type": "String", "text": "\"a\"", "line": 1, "column": 9, "span": { "start": 8, "end": 11 } })
+    );
+
+    // Scanning doesn't care whether the file parses.
+    let tokens = entry(&files, "b.gml")["tokens"].as_array().unwrap();
+    assert_eq!(tokens[0]["type"], "Switch");
+    assert_eq!(tokens[2]["type"], "Identifier");
+}
+
+#[test]
+fn emit_ast_json_gives_each_file_its_tree_or_its_problems() {
+    let project = Project::new("ast_json");
+    let files: Value = serde_json::from_str(&emit(&project.0, "ast-json")).unwrap();
+
+    let parsed = entry(&files, "a.gml");
+    assert!(parsed.get("diagnostics").is_none());
+    let statement = &parsed["ast"]["statements"][0];
+    assert_eq!(statement["span"], json!({ "start": 0, "end": 12 }));
+    let declaration = &statement["statement"]["VariableDeclList"];
+    assert_eq!(declaration["starting_var_type"]["token_type"], "Var");
+
+    let broken = entry(&files, "b.gml");
+    assert!(broken.get("ast").is_none());
+    let diagnostics = broken["diagnostics"].as_array().unwrap();
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0]["message"], "expected `case` or `default`, found `x`");
+    assert_eq!(
+        (&diagnostics[0]["line"], &diagnostics[0]["column"]),
+        (&json!(1), &json!(14))
+    );
+}
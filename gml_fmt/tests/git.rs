@@ -0,0 +1,149 @@
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// A git repository in a folder of its own, removed when the test ends.
+struct Repo {
+    root: PathBuf,
+}
+
+impl Repo {
+    /// The repository is `proj` inside a fresh folder, so tests can run gml_fmt
+    /// from that folder with a relative path.
+    fn new(name: &str) -> Repo {
+        let root = std::env::temp_dir().join(format!("gml_fmt_git_{}_{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(root.join("proj")).unwrap();
+
+        let repo = Repo { root };
+        repo.git(&["init", "-q"]);
+        repo
+    }
+
+    fn proj(&self) -> PathBuf {
+        self.root.join("proj")
+    }
+
+    fn write(&self, relative: &str, contents: &str) {
+        let path = self.proj().join(relative);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, contents).unwrap();
+    }
+
+    fn read(&self, relative: &str) -> String {
+        std::fs::read_to_string(self.proj().join(relative)).unwrap()
+    }
+
+    fn git(&self, args: &[&str]) -> String {
+        let output = Command::new("git")
+            .current_dir(self.proj())
+            .args(args)
+            .env("GIT_AUTHOR_NAME", "gml_fmt")
+            .env("GIT_AUTHOR_EMAIL", "gml_fmt@example.com")
+            .env("GIT_COMMITTER_NAME", "gml_fmt")
+            .env("GIT_COMMITTER_EMAIL", "gml_fmt@example.com")
+            .output()
+            .expect("Could not run git");
+        assert!(
+            output.status.success(),
+            "git {:?} failed: {}",
+            args,
+            String::from_utf8_lossy(&output.stderr)
+        );
+        String::from_utf8(output.stdout).unwrap()
+    }
+
+    fn commit(&self, message: &str) {
+        self.git(&["add", "-A"]);
+        self.git(&["commit", "-q", "-m", message]);
+    }
+
+    /// Runs gml_fmt from the folder holding the repository, on the relative path `proj`.
+    fn gml_fmt(&self, args: &[&str]) {
+        let output = Command::new(env!("CARGO_BIN_EXE_gml_fmt"))
+            .current_dir(&self.root)
+            .arg(Path::new("proj"))
+            .args(args)
+            .output()
+            .expect("Could not start gml_fmt");
+        assert!(
+            output.status.success(),
+            "gml_fmt {:?} failed: {}",
+            args,
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+}
+
+impl Drop for Repo {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.root);
+    }
+}
+
+#[test]
+fn staged_formats_the_index_through_a_relative_path() {
+    let repo = Repo::new("staged");
+    repo.write("a.gml", "x=1;\n");
+    repo.write("sub/b.gml", "y=2;\n");
+    repo.git(&["add", "-A"]);
+
+    // An unstaged edit must survive, since only what was staged is formatted.
+    repo.write("a.gml", "x=1;\nw=0;\n");
+    repo.gml_fmt(&["--staged"]);
+
+    assert_eq!(repo.git(&["show", ":a.gml"]), "x = 1;\n");
+    assert_eq!(repo.git(&["show", ":sub/b.gml"]), "y = 2;\n");
+    assert_eq!(repo.read("a.gml"), "x=1;\nw=0;\n");
+    // The working copy matched the index, so it's formatted too.
+    assert_eq!(repo.read("sub/b.gml"), "y = 2;\n");
+}
+
+#[test]
+fn staged_leaves_files_which_arent_utf8_alone() {
+    let repo = Repo::new("staged_latin1");
+    std::fs::write(repo.proj().join("latin1.gml"), b"x=\"caf\xE9\";\n").unwrap();
+    repo.write("a.gml", "x=1;\n");
+    repo.git(&["add", "-A"]);
+
+    let before = repo.git(&["rev-parse", ":latin1.gml"]);
+    repo.gml_fmt(&["--staged"]);
+
+    assert_eq!(repo.git(&["rev-parse", ":latin1.gml"]), before);
+    assert_eq!(
+        std::fs::read(repo.proj().join("latin1.gml")).unwrap(),
+        b"x=\"caf\xE9\";\n"
+    );
+    assert_eq!(repo.git(&["show", ":a.gml"]), "x = 1;\n");
+}
+
+#[test]
+fn changed_formats_modified_and_untracked_files() {
+    let repo = Repo::new("changed");
+    repo.write("modified.gml", "x=1;\n");
+    repo.write("untouched.gml", "y=2;\n");
+    repo.commit("First");
+
+    repo.write("modified.gml", "x=3;\n");
+    repo.write("sub/untracked.gml", "z=4;\n");
+    repo.gml_fmt(&["--changed"]);
+
+    assert_eq!(repo.read("modified.gml"), "x = 3;\n");
+    assert_eq!(repo.read("sub/untracked.gml"), "z = 4;\n");
+    assert_eq!(repo.read("untouched.gml"), "y=2;\n");
+}
+
+#[test]
+fn since_formats_files_changed_after_a_revision() {
+    let repo = Repo::new("since");
+    repo.write("old.gml", "x=1;\n");
+    repo.write("new.gml", "y=2;\n");
+    repo.commit("First");
+    repo.git(&["tag", "base"]);
+
+    repo.write("new.gml", "y=3;\n");
+    repo.commit("Second");
+    repo.gml_fmt(&["--since", "base"]);
+
+    assert_eq!(repo.read("new.gml"), "y = 3;\n");
+    assert_eq!(repo.read("old.gml"), "x=1;\n");
+}
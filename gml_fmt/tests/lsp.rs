@@ -0,0 +1,192 @@
+use serde_json::{json, Value};
+use std::io::{Read, Write};
+use std::process::{Command, Stdio};
+
+fn frame(message: &Value) -> String {
+    let content = message.to_string();
+    format!("Content-Length: {}\r\n\r\n{}", content.len(), content)
+}
+
+fn unframe(mut output: &str) -> Vec<Value> {
+    let mut messages = Vec::new();
+    while let Some((header, rest)) = output.split_once("\r\n\r\n") {
+        let length: usize = header.trim_start_matches("Content-Length: ").parse().unwrap();
+        messages.push(serde_json::from_str(&rest[..length]).unwrap());
+        output = &rest[length..];
+    }
+    messages
+}
+
+/// Sends `messages` to `gml_fmt lsp`, and gives back everything it sent back once it exits.
+fn run_session(messages: &[Value]) -> Vec<Value> {
+    run_script(&messages.iter().map(frame).collect::<String>())
+}
+
+/// Sends `script`, already framed, to `gml_fmt lsp`, for messages which aren't JSON.
+fn run_script(script: &str) -> Vec<Value> {
+    let mut server = Command::new(env!("CARGO_BIN_EXE_gml_fmt"))
+        .arg("lsp")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .expect("Could not start the language server");
+
+    server.stdin.take().unwrap().write_all(script.as_bytes()).unwrap();
+
+    let mut output = String::new();
+    server.stdout.take().unwrap().read_to_string(&mut output).unwrap();
+    assert!(server.wait().unwrap().success());
+
+    unframe(&output)
+}
+
+fn request(id: u64, method: &str, params: Value) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params })
+}
+
+fn notification(method: &str, params: Value) -> Value {
+    json!({ "jsonrpc": "2.0", "method": method, "params": params })
+}
+
+#[test]
+fn lsp_formats_and_reports_diagnostics() {
+    let good = "file:///project/good.gml";
+    let broken = "file:///project/broken.gml";
+
+    let responses = run_session(&[
+        request(1, "initialize", json!({ "capabilities": {} })),
+        notification("initialized", json!({})),
+        notification(
+            "textDocument/didOpen",
+            json!({ "textDocument": { "uri": good, "languageId": "gml", "version": 1, "text": "if (a) {\nx=1;\n}\n" } }),
+        ),
+        notification(
+            "textDocument/didOpen",
+            json!({ "textDocument": { "uri": broken, "languageId": "gml", "version": 1, "text": "switch (a) { x = 2; }\n" } }),
+        ),
+        request(
+            2,
+            "textDocument/formatting",
+            json!({ "textDocument": { "uri": good }, "options": {} }),
+        ),
+        request(
+            3,
+            "textDocument/rangeFormatting",
+            json!({
+                "textDocument": { "uri": good },
+                "range": { "start": { "line": 1, "character": 0 }, "end": { "line": 1, "character": 4 } },
+                "options": {},
+            }),
+        ),
+        request(
+            4,
+            "textDocument/onTypeFormatting",
+            json!({ "textDocument": { "uri": good }, "position": { "line": 2, "character": 1 }, "ch": "}", "options": {} }),
+        ),
+        request(
+            5,
+            "textDocument/formatting",
+            json!({ "textDocument": { "uri": broken }, "options": {} }),
+        ),
+        request(6, "textDocument/hover", json!({})),
+        request(7, "shutdown", Value::Null),
+        notification("exit", Value::Null),
+    ]);
+
+    let capabilities = &responses[0]["result"]["capabilities"];
+    assert_eq!(capabilities["documentFormattingProvider"], true);
+    assert_eq!(
+        capabilities["documentOnTypeFormattingProvider"],
+        json!({ "firstTriggerCharacter": "}", "moreTriggerCharacter": [";"] })
+    );
+
+    assert_eq!(responses[1]["method"], "textDocument/publishDiagnostics");
+    assert_eq!(responses[1]["params"], json!({ "uri": good, "diagnostics": [] }));
+
+    let diagnostics = &responses[2]["params"]["diagnostics"];
+    assert_eq!(diagnostics.as_array().unwrap().len(), 1);
+    assert_eq!(
+        diagnostics[0]["range"],
+        json!({ "start": { "line": 0, "character": 13 }, "end": { "line": 0, "character": 14 } })
+    );
+    assert_eq!(diagnostics[0]["severity"], 1);
+
+    let edit = json!([{
+        "range": { "start": { "line": 1, "character": 0 }, "end": { "line": 2, "character": 0 } },
+        "newText": "    x = 1;\n",
+    }]);
+    for (response, id) in responses[3..6].iter().zip(2..) {
+        assert_eq!(response["id"], id);
+        assert_eq!(response["result"], edit);
+    }
+
+    // What doesn't parse is left as it was, so there's nothing to change.
+    assert_eq!(responses[6]["result"], json!([]));
+    assert_eq!(responses[7]["error"]["code"], -32601);
+    assert_eq!(responses[8], json!({ "jsonrpc": "2.0", "id": 7, "result": null }));
+    assert_eq!(responses.len(), 9);
+}
+
+#[test]
+fn lsp_formats_selected_lines_and_closed_blocks() {
+    let uri = "file:///project/lines.gml";
+    let text = "x=1;\ny=2;\nif (a) {\nif (b) {\nz=3;\n}\n}\nw=4;\n";
+
+    let responses = run_session(&[
+        request(1, "initialize", json!({ "capabilities": {} })),
+        notification(
+            "textDocument/didOpen",
+            json!({ "textDocument": { "uri": uri, "languageId": "gml", "version": 1, "text": text } }),
+        ),
+        // Selecting the whole of the first line ends the selection at the start of the second.
+        request(
+            2,
+            "textDocument/rangeFormatting",
+            json!({
+                "textDocument": { "uri": uri },
+                "range": { "start": { "line": 0, "character": 0 }, "end": { "line": 1, "character": 0 } },
+                "options": {},
+            }),
+        ),
+        // Typing the `}` which closes the inner block.
+        request(
+            3,
+            "textDocument/onTypeFormatting",
+            json!({ "textDocument": { "uri": uri }, "position": { "line": 5, "character": 1 }, "ch": "}", "options": {} }),
+        ),
+        request(4, "shutdown", Value::Null),
+        notification("exit", Value::Null),
+    ]);
+
+    assert_eq!(
+        responses[2]["result"],
+        json!([{
+            "range": { "start": { "line": 0, "character": 0 }, "end": { "line": 1, "character": 0 } },
+            "newText": "x = 1;\n",
+        }])
+    );
+    assert_eq!(
+        responses[3]["result"],
+        json!([{
+            "range": { "start": { "line": 3, "character": 0 }, "end": { "line": 6, "character": 0 } },
+            "newText": "    if (b) {\n        z = 3;\n    }\n",
+        }])
+    );
+}
+
+#[test]
+fn lsp_answers_a_message_which_isnt_json_and_keeps_going() {
+    let script = [
+        frame(&request(1, "initialize", json!({ "capabilities": {} }))),
+        "Content-Length: 9\r\n\r\n{\"id\": 2,".to_owned(),
+        frame(&request(3, "shutdown", Value::Null)),
+        frame(&notification("exit", Value::Null)),
+    ]
+    .concat();
+    let responses = run_script(&script);
+
+    assert_eq!(responses.len(), 3);
+    assert_eq!(responses[1]["id"], Value::Null);
+    assert_eq!(responses[1]["error"]["code"], -32700);
+    assert_eq!(responses[2], json!({ "jsonrpc": "2.0", "id": 3, "result": null }));
+}
@@ -0,0 +1,139 @@
+use serde_json::Value;
+use std::collections::BTreeSet;
+use std::process::Command;
+
+fn keys(value: &Value) -> BTreeSet<&str> {
+    value.as_object().unwrap().keys().map(String::as_str).collect()
+}
+
+#[test]
+fn json_report_describes_every_kind_of_file() {
+    let project = std::env::temp_dir().join(format!("gml_fmt_report_test_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&project);
+    std::fs::create_dir_all(&project).unwrap();
+    std::fs::write(project.join("a_reformatted.gml"), "x=1;\n").unwrap();
+    std::fs::write(project.join("b_unchanged.gml"), "x = 1;\n").unwrap();
+    std::fs::write(project.join("c_ignored.gml"), "// @gml_fmt ignore\nx=1;\n").unwrap();
+    std::fs::write(project.join("d_parse_error.gml"), "x = (1;\n").unwrap();
+    // The printer drops `break` after a one-line `case`, which verification catches.
+    std::fs::write(
+        project.join("e_verification.gml"),
+        "switch (q) { case 1: break; default: exit }\n",
+    )
+    .unwrap();
+    // The broken `switch` is kept as it was, but the line above it is still formatted.
+    std::fs::write(
+        project.join("f_formatted_with_errors.gml"),
+        "var  a=1;\nswitch (a) {\n    x = 2;\n}\n",
+    )
+    .unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_gml_fmt"))
+        .arg(&project)
+        .args(["--report", "json"])
+        .output()
+        .expect("Could not start gml_fmt");
+    std::fs::remove_dir_all(&project).unwrap();
+
+    // A verification failure fails the run, but the report is still printed.
+    assert_eq!(output.status.code(), Some(1));
+    let report: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(keys(&report), BTreeSet::from(["files", "totals"]));
+
+    let files = report["files"].as_array().unwrap();
+    let statuses: Vec<_> = files.iter().map(|file| file["status"].as_str().unwrap()).collect();
+    assert_eq!(
+        statuses,
+        [
+            "reformatted",
+            "unchanged",
+            "ignored",
+            "parse_error",
+            "verification_failed",
+            "formatted_with_errors"
+        ]
+    );
+
+    let plain = BTreeSet::from(["path", "status", "bytes_before", "bytes_after", "time_ms"]);
+    let with_error = BTreeSet::from([
+        "path",
+        "status",
+        "error",
+        "bytes_before",
+        "bytes_after",
+        "time_ms",
+        "diagnostics",
+    ]);
+    for file in &files[..3] {
+        assert_eq!(keys(file), plain, "{}", file);
+    }
+    for file in &files[3..] {
+        assert_eq!(keys(file), with_error, "{}", file);
+        assert_eq!(
+            keys(&file["error"]),
+            BTreeSet::from(["message", "line", "column", "help"])
+        );
+        assert_eq!(file["error"]["message"], file["diagnostics"][0]["message"]);
+        assert_eq!(file["error"]["line"], file["diagnostics"][0]["line"]);
+        assert_eq!(file["error"]["column"], file["diagnostics"][0]["column"]);
+        assert_eq!(
+            keys(&file["diagnostics"][0]),
+            BTreeSet::from(["severity", "message", "line", "column", "span", "help"])
+        );
+    }
+
+    assert!(files[0]["path"].as_str().unwrap().ends_with("a_reformatted.gml"));
+    assert_eq!(
+        (&files[0]["bytes_before"], &files[0]["bytes_after"]),
+        (&5.into(), &7.into())
+    );
+    assert_eq!(files[3]["error"]["message"], "unexpected end of file");
+    assert_eq!(
+        (&files[3]["error"]["line"], &files[3]["error"]["column"]),
+        (&1.into(), &8.into())
+    );
+    assert_eq!(files[5]["error"]["message"], "expected `case` or `default`, found `x`");
+    assert_eq!(
+        (&files[5]["bytes_before"], &files[5]["bytes_after"]),
+        (&36.into(), &37.into())
+    );
+    // Files we didn't change keep their size.
+    for file in &files[1..5] {
+        assert_eq!(file["bytes_before"], file["bytes_after"], "{}", file);
+    }
+
+    let totals = &report["totals"];
+    assert_eq!(
+        keys(totals),
+        BTreeSet::from([
+            "files",
+            "unchanged",
+            "reformatted",
+            "parse_errors",
+            "formatted_with_errors",
+            "ignored",
+            "failed",
+            "verification_failures",
+            "bytes_before",
+            "bytes_after",
+            "time_ms",
+        ])
+    );
+    for (total, expected) in [
+        ("files", 6),
+        ("unchanged", 1),
+        ("reformatted", 1),
+        ("parse_errors", 1),
+        ("formatted_with_errors", 1),
+        ("ignored", 1),
+        ("failed", 0),
+        ("verification_failures", 1),
+    ] {
+        assert_eq!(totals[total], expected, "totals.{}", total);
+    }
+    for total in ["bytes_before", "bytes_after"] {
+        let sum: u64 = files.iter().map(|file| file[total].as_u64().unwrap()).sum();
+        assert_eq!(totals[total], sum, "totals.{}", total);
+    }
+    assert!(totals["time_ms"].as_f64().unwrap() >= 0.0);
+}
@@ -0,0 +1,87 @@
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+use std::process::{Child, Command, Stdio};
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+use std::time::Duration;
+
+/// Long enough for a slow CI machine, short enough that a hung watcher fails the run.
+const TIMEOUT: Duration = Duration::from_secs(20);
+
+/// Kills the watcher when a test ends, even if it panicked.
+struct Watcher {
+    child: Child,
+    lines: Receiver<String>,
+}
+
+impl Watcher {
+    fn start(dir: &Path) -> Watcher {
+        let mut child = Command::new(env!("CARGO_BIN_EXE_gml_fmt"))
+            .arg(dir)
+            .arg("--watch")
+            .stdout(Stdio::piped())
+            .spawn()
+            .expect("Could not start gml_fmt --watch");
+
+        let (tx, lines) = mpsc::channel();
+        let stdout = child.stdout.take().unwrap();
+        thread::spawn(move || {
+            for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+                if tx.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+
+        let watcher = Watcher { child, lines };
+        watcher.wait_for("Watching");
+        watcher
+    }
+
+    /// Waits for a line of output containing `text`, and gives back every line before it.
+    fn wait_for(&self, text: &str) -> Vec<String> {
+        let mut seen = Vec::new();
+        loop {
+            match self.lines.recv_timeout(TIMEOUT) {
+                Ok(line) if line.contains(text) => return seen,
+                Ok(line) => seen.push(line),
+                Err(_) => panic!("gml_fmt --watch never printed {:?}. It printed {:?}", text, seen),
+            }
+        }
+    }
+}
+
+impl Drop for Watcher {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+#[test]
+fn watch_formats_saved_files_and_leaves_ignored_ones() {
+    let project = std::env::temp_dir().join(format!("gml_fmt_watch_test_{}", std::process::id()));
+    std::fs::create_dir_all(&project).unwrap();
+    std::fs::write(project.join("existing.gml"), "x=1;\n").unwrap();
+
+    let watcher = Watcher::start(&project);
+
+    std::fs::write(project.join("ignored.gml"), "// @gml_fmt ignore\nx=1;\n").unwrap();
+    std::fs::write(project.join("existing.gml"), "y=2;\n").unwrap();
+    let before = watcher.wait_for("Formatted");
+
+    // A file created while watching is picked up too.
+    std::fs::write(project.join("new.gml"), "z=3;\n").unwrap();
+    let between = watcher.wait_for("Formatted");
+
+    let existing = std::fs::read_to_string(project.join("existing.gml")).unwrap();
+    let new = std::fs::read_to_string(project.join("new.gml")).unwrap();
+    let ignored = std::fs::read_to_string(project.join("ignored.gml")).unwrap();
+    drop(watcher);
+    std::fs::remove_dir_all(&project).unwrap();
+
+    assert_eq!(existing, "y = 2;\n");
+    assert_eq!(new, "z = 3;\n");
+    assert_eq!(ignored, "// @gml_fmt ignore\nx=1;\n");
+    assert!(before.iter().chain(&between).all(|line| !line.contains("ignored")));
+}
@@ -0,0 +1,81 @@
+/*
+ * gml_fmt's C ABI, from the gml_fmt_ffi crate. Link against the gml_fmt_ffi
+ * shared library (libgml_fmt_ffi.so, libgml_fmt_ffi.dylib or gml_fmt_ffi.dll).
+ */
+
+#ifndef GML_FMT_H
+#define GML_FMT_H
+
+/*
+ * Generated from gml_fmt_ffi/src/lib.rs by cbindgen. Don't edit it by hand: run
+ * `GML_FMT_UPDATE_HEADER=1 cargo test -p gml_fmt_ffi --test header` instead.
+ */
+
+#include <stddef.h>
+
+/**
+ * The source was formatted, and `output` holds the result.
+ */
+#define GML_FMT_OK 0
+
+/**
+ * The source couldn't be parsed. `error` says where.
+ */
+#define GML_FMT_PARSE_ERROR 1
+
+/**
+ * Formatting would have changed more than whitespace, so nothing was given back.
+ */
+#define GML_FMT_VERIFICATION_FAILED 2
+
+/**
+ * The source wasn't UTF-8 or held a NUL, the config wasn't valid TOML, or a pointer was null.
+ */
+#define GML_FMT_INVALID_ARGUMENT 3
+
+/**
+ * gml_fmt hit a bug and gave up.
+ */
+#define GML_FMT_PANIC 4
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * Formats the `len` bytes of UTF-8 at `src`. `config_toml` is a NUL-terminated
+ * `gml_fmt.toml`, or null for the defaults. Since we give back NUL-terminated
+ * strings, a `src` holding a NUL is rejected rather than formatted. A `src` which
+ * asks to be left alone with `// @gml_fmt ignore` is given back as it was.
+ *
+ * On `GML_FMT_OK`, `*output` is the formatted source and `*output_len` its length
+ * in bytes. Otherwise, `*error` describes what went wrong. Whichever we set is
+ * NUL-terminated, owned by the caller and freed with `gml_fmt_free`; the others
+ * are set to null and 0.
+ *
+ * # Safety
+ *
+ * `src` must point to `len` readable bytes, `config_toml` must be null or a
+ * NUL-terminated string, and `output`, `output_len` and `error` must be valid to write.
+ */
+int gml_fmt_format(const char *src,
+                   size_t len,
+                   const char *config_toml,
+                   char **output,
+                   size_t *output_len,
+                   char **error);
+
+/**
+ * Frees a string `gml_fmt_format` gave back. Freeing null does nothing.
+ *
+ * # Safety
+ *
+ * `buffer` must be null or a string from `gml_fmt_format` which hasn't been freed yet.
+ */
+void gml_fmt_free(char *buffer);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* GML_FMT_H */
@@ -0,0 +1,150 @@
+//! A C ABI for gml_fmt, so editors and tools which aren't written in Rust can
+//! format in-process rather than spawning the CLI. `include/gml_fmt.h` declares
+//! everything here for C, and is generated from this file by cbindgen; the
+//! `header` test fails if it's out of date.
+
+#![allow(clippy::bool_comparison)]
+
+use gml_fmt_lib::{render_error, verify_tokens, LangConfig};
+use std::ffi::{CStr, CString};
+use std::os::raw::{c_char, c_int};
+use std::panic::{self, AssertUnwindSafe};
+use std::path::Path;
+use std::{ptr, slice, str};
+
+/// The source was formatted, and `output` holds the result.
+pub const GML_FMT_OK: c_int = 0;
+/// The source couldn't be parsed. `error` says where.
+pub const GML_FMT_PARSE_ERROR: c_int = 1;
+/// Formatting would have changed more than whitespace, so nothing was given back.
+pub const GML_FMT_VERIFICATION_FAILED: c_int = 2;
+/// The source wasn't UTF-8 or held a NUL, the config wasn't valid TOML, or a pointer was null.
+pub const GML_FMT_INVALID_ARGUMENT: c_int = 3;
+/// gml_fmt hit a bug and gave up.
+pub const GML_FMT_PANIC: c_int = 4;
+
+/// Formats the `len` bytes of UTF-8 at `src`. `config_toml` is a NUL-terminated
+/// `gml_fmt.toml`, or null for the defaults. Since we give back NUL-terminated
+/// strings, a `src` holding a NUL is rejected rather than formatted. A `src` which
+/// asks to be left alone with `// @gml_fmt ignore` is given back as it was.
+///
+/// On `GML_FMT_OK`, `*output` is the formatted source and `*output_len` its length
+/// in bytes. Otherwise, `*error` describes what went wrong. Whichever we set is
+/// NUL-terminated, owned by the caller and freed with `gml_fmt_free`; the others
+/// are set to null and 0.
+///
+/// # Safety
+///
+/// `src` must point to `len` readable bytes, `config_toml` must be null or a
+/// NUL-terminated string, and `output`, `output_len` and `error` must be valid to write.
+#[no_mangle]
+pub unsafe extern "C" fn gml_fmt_format(
+    src: *const c_char,
+    len: usize,
+    config_toml: *const c_char,
+    output: *mut *mut c_char,
+    output_len: *mut usize,
+    error: *mut *mut c_char,
+) -> c_int {
+    if output.is_null() || output_len.is_null() || error.is_null() {
+        return GML_FMT_INVALID_ARGUMENT;
+    }
+    *output = ptr::null_mut();
+    *output_len = 0;
+    *error = ptr::null_mut();
+
+    if src.is_null() && len > 0 {
+        *error = into_c_string("src is null".to_owned());
+        return GML_FMT_INVALID_ARGUMENT;
+    }
+    let src = if len == 0 {
+        &[]
+    } else {
+        slice::from_raw_parts(src as *const u8, len)
+    };
+    let config_toml = if config_toml.is_null() {
+        None
+    } else {
+        Some(CStr::from_ptr(config_toml).to_bytes())
+    };
+
+    // Unwinding into C is undefined behaviour, so a panic has to stop here.
+    let result = panic::catch_unwind(AssertUnwindSafe(|| format(src, config_toml)));
+    let (status, text) = match result {
+        // `src` has no NULs, and formatting only moves whitespace around, so neither
+        // does the output. If it somehow did, we'd rather fail than hand back less.
+        Ok(Ok(formatted)) => match CString::new(formatted) {
+            Ok(formatted) => {
+                *output_len = formatted.as_bytes().len();
+                *output = formatted.into_raw();
+                return GML_FMT_OK;
+            }
+            Err(_) => (
+                GML_FMT_PANIC,
+                "gml_fmt put a NUL in its output. Please report it with this file!".to_owned(),
+            ),
+        },
+        Ok(Err(failure)) => failure,
+        Err(_) => (
+            GML_FMT_PANIC,
+            "gml_fmt panicked. Please report it with this file!".to_owned(),
+        ),
+    };
+
+    *error = into_c_string(text);
+    status
+}
+
+/// Frees a string `gml_fmt_format` gave back. Freeing null does nothing.
+///
+/// # Safety
+///
+/// `buffer` must be null or a string from `gml_fmt_format` which hasn't been freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn gml_fmt_free(buffer: *mut c_char) {
+    if buffer.is_null() == false {
+        drop(CString::from_raw(buffer));
+    }
+}
+
+fn format(src: &[u8], config_toml: Option<&[u8]>) -> Result<String, (c_int, String)> {
+    let source = str::from_utf8(src).map_err(|e| (GML_FMT_INVALID_ARGUMENT, format!("src is not UTF-8: {}", e)))?;
+    if let Some(offset) = source.find('\0') {
+        return Err((
+            GML_FMT_INVALID_ARGUMENT,
+            format!(
+                "src holds a NUL at byte {}, which can't be given back in a C string",
+                offset
+            ),
+        ));
+    }
+
+    let lang_config: LangConfig = match config_toml {
+        Some(config_toml) => str::from_utf8(config_toml)
+            .map_err(|e| e.to_string())
+            .and_then(|config_toml| toml::from_str(config_toml).map_err(|e| e.to_string()))
+            .map_err(|e| (GML_FMT_INVALID_ARGUMENT, format!("Could not parse config: {}", e)))?,
+        None => LangConfig::default(),
+    };
+
+    // There's no file, so there's no `.gmx` to format piece by piece either.
+    if gml_fmt_lib::ignores_file(Path::new(""), source) {
+        return Ok(source.to_owned());
+    }
+
+    let output = gml_fmt_lib::run(source, &lang_config, None)
+        .map_err(|e| (GML_FMT_PARSE_ERROR, render_error(&e, source, None)))?;
+
+    if let Err(diagnostic) = verify_tokens(source, &output) {
+        return Err((GML_FMT_VERIFICATION_FAILED, diagnostic.render(source, None)));
+    }
+
+    Ok(output)
+}
+
+/// Only for error messages, which quote the source. Any NUL is written out as
+/// `\0`, so the message says so rather than being cut short.
+fn into_c_string(text: String) -> *mut c_char {
+    let text = CString::new(text.replace('\0', "\\0")).unwrap_or_default();
+    text.into_raw()
+}
@@ -0,0 +1,69 @@
+/* Formats through the C ABI, the way an embedding editor would. Exits non-zero on any failure. */
+
+#include "gml_fmt.h"
+
+#include <stdio.h>
+#include <string.h>
+
+static int failures = 0;
+
+static void expect(int condition, const char *what) {
+    if (!condition) {
+        fprintf(stderr, "failed: %s\n", what);
+        failures++;
+    }
+}
+
+int main(void) {
+    char *output = NULL;
+    size_t output_len = 0;
+    char *error = NULL;
+
+    const char *src = "if (a) {\nx=1;\n}\n";
+    int status = gml_fmt_format(src, strlen(src), NULL, &output, &output_len, &error);
+    expect(status == GML_FMT_OK, "formatting valid source succeeds");
+    expect(error == NULL, "no error on success");
+    expect(output != NULL && strcmp(output, "if (a) {\n    x = 1;\n}\n") == 0, "output is formatted");
+    expect(output != NULL && output_len == strlen(output), "output_len matches the output");
+    gml_fmt_free(output);
+
+    status = gml_fmt_format(src, strlen(src), "use_spaces = false", &output, &output_len, &error);
+    expect(status == GML_FMT_OK, "formatting with a config succeeds");
+    expect(output != NULL && strcmp(output, "if (a) {\n\tx = 1;\n}\n") == 0, "config is used");
+    gml_fmt_free(output);
+
+    const char *ignored = "// @gml_fmt ignore\nswitch (a) { x=2; }\n";
+    status = gml_fmt_format(ignored, strlen(ignored), NULL, &output, &output_len, &error);
+    expect(status == GML_FMT_OK, "a source asking to be ignored succeeds");
+    expect(output != NULL && strcmp(output, ignored) == 0, "a source asking to be ignored is given back as it was");
+    gml_fmt_free(output);
+
+    const char *broken = "switch (a) { x = 2; }\n";
+    status = gml_fmt_format(broken, strlen(broken), NULL, &output, &output_len, &error);
+    expect(status == GML_FMT_PARSE_ERROR, "broken source is a parse error");
+    expect(output == NULL && output_len == 0, "no output on failure");
+    expect(error != NULL && strstr(error, "expected `case` or `default`") != NULL, "error says why");
+    gml_fmt_free(error);
+
+    status = gml_fmt_format(src, strlen(src), "use_spaces = ", &output, &output_len, &error);
+    expect(status == GML_FMT_INVALID_ARGUMENT, "a broken config is rejected");
+    gml_fmt_free(error);
+
+    status = gml_fmt_format("\xff", 1, NULL, &output, &output_len, &error);
+    expect(status == GML_FMT_INVALID_ARGUMENT, "non-UTF-8 source is rejected");
+    gml_fmt_free(error);
+
+    const char with_nul[] = "x = \"a\0b\";\n";
+    status = gml_fmt_format(with_nul, sizeof with_nul - 1, NULL, &output, &output_len, &error);
+    expect(status == GML_FMT_INVALID_ARGUMENT, "source holding a NUL is rejected");
+    expect(output == NULL && output_len == 0, "no output for a source holding a NUL");
+    expect(error != NULL && strstr(error, "NUL at byte 6") != NULL, "error says where the NUL is");
+    gml_fmt_free(error);
+
+    gml_fmt_free(NULL);
+
+    if (failures == 0) {
+        printf("ok\n");
+    }
+    return failures == 0 ? 0 : 1;
+}
@@ -0,0 +1,43 @@
+#![cfg(unix)]
+
+use std::env;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Cargo builds our shared library next to the test binaries.
+fn library_dir() -> PathBuf {
+    let test_binary = env::current_exe().unwrap();
+    test_binary.parent().unwrap().to_path_buf()
+}
+
+#[test]
+fn c_program_formats_through_the_abi() {
+    let crate_dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let library_dir = library_dir();
+    let program = Path::new(env!("CARGO_TARGET_TMPDIR")).join("gml_fmt_format_test");
+
+    let compiler = env::var("CC").unwrap_or_else(|_| "cc".to_owned());
+    let status = Command::new(&compiler)
+        .arg(crate_dir.join("tests/c/format_test.c"))
+        .arg("-I")
+        .arg(crate_dir.join("include"))
+        .arg("-L")
+        .arg(&library_dir)
+        .arg(format!("-Wl,-rpath,{}", library_dir.display()))
+        .arg("-lgml_fmt_ffi")
+        .arg("-Wall")
+        .arg("-Werror")
+        .arg("-o")
+        .arg(&program)
+        .status()
+        .unwrap_or_else(|e| panic!("Could not run the C compiler {:?}: {}", compiler, e));
+    assert!(status.success(), "The C test program didn't compile.");
+
+    let output = Command::new(&program).output().unwrap();
+    assert!(
+        output.status.success(),
+        "The C test program failed:\n{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "ok\n");
+}
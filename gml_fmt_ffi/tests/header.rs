@@ -0,0 +1,32 @@
+use std::env;
+use std::fs;
+use std::path::Path;
+
+/// The header is generated from our Rust, so it can't drift out of step with it.
+/// Set `GML_FMT_UPDATE_HEADER` to write the new header instead of failing.
+#[test]
+fn header_matches_the_rust() {
+    let crate_dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let header_path = crate_dir.join("include/gml_fmt.h");
+
+    let config = cbindgen::Config::from_file(crate_dir.join("cbindgen.toml")).unwrap();
+    let mut generated = Vec::new();
+    cbindgen::Builder::new()
+        .with_crate(crate_dir)
+        .with_config(config)
+        .generate()
+        .expect("Could not generate the header")
+        .write(&mut generated);
+    let generated = String::from_utf8(generated).unwrap();
+
+    if env::var_os("GML_FMT_UPDATE_HEADER").is_some() {
+        fs::write(&header_path, &generated).unwrap();
+        return;
+    }
+
+    let checked_in = fs::read_to_string(&header_path).unwrap();
+    assert!(
+        checked_in == generated,
+        "include/gml_fmt.h is out of date. Run `GML_FMT_UPDATE_HEADER=1 cargo test -p gml_fmt_ffi --test header`."
+    );
+}
@@ -2,8 +2,36 @@
 extern crate criterion;
 
 use criterion::Criterion;
-use gml_fmt_lib::{Config, LangConfig, PrintFlags};
-use std::{path::PathBuf, process};
+use gml_fmt_lib::{Config, LangConfig, PrintFlags, Silent};
+use std::path::{Path, PathBuf};
+use std::{fs, process};
+
+const PROJECT_FILE_COUNT: usize = 400;
+const PROJECT_SAMPLE: &str = "/// @description Step
+var spd = 4, dir = point_direction(x, y, mouse_x, mouse_y);
+if (keyboard_check(vk_left))  { x -= spd }
+else if (keyboard_check(vk_right)) {
+x += spd;
+}
+
+for (var i = 0; i < ds_list_size(enemies); i++) {
+    var enemy = enemies[| i];
+    if (point_distance(x, y, enemy.x, enemy.y) < 32 && enemy.hp > 0)
+    {
+        enemy.hp -= damage;
+        audio_play_sound(snd_hit, 0, false)
+    }
+}
+
+switch (state) {
+    case states.idle: sprite_index = spr_idle; break;
+    case states.walk:
+        sprite_index = spr_walk;
+        image_speed = spd / 4;
+        break;
+    default: break;
+}
+";
 
 fn lex_test() {
     let path = PathBuf::from("benches/samples/osg_lex_speed.gml");
@@ -12,12 +40,44 @@ fn lex_test() {
         process::exit(1);
     });
 
-    gml_fmt_lib::run_with_config(&config, &LangConfig::default())
+    gml_fmt_lib::run_with_config(&config, &LangConfig::default(), &mut Silent)
         .expect("Attempted to run osg_lex_speed test, but failed. Did you move the file?");
 }
 
+/// Writes a fake project of many small files, so we can see how we scale across them.
+fn make_project() -> PathBuf {
+    let project = std::env::temp_dir().join("gml_fmt_bench_project");
+    for i in 0..PROJECT_FILE_COUNT {
+        let folder = project.join(format!("obj_{}", i / 20));
+        fs::create_dir_all(&folder).expect("Could not create the benchmark project.");
+        fs::write(folder.join(format!("Step_{}.gml", i)), PROJECT_SAMPLE)
+            .expect("Could not write the benchmark project.");
+    }
+    project
+}
+
+fn project_test(project: &Path, jobs: usize) {
+    let mut config = Config::new(project.to_path_buf(), PrintFlags::empty(), false).unwrap_or_else(|e| {
+        eprintln!("File reading error: {}", e);
+        process::exit(1);
+    });
+    config.jobs = jobs;
+
+    gml_fmt_lib::run_with_config(&config, &LangConfig::default(), &mut Silent)
+        .expect("Attempted to format the project, but failed.");
+}
+
 fn criterion_benchmark(c: &mut Criterion) {
-    c.bench_function("osg_lex_speed", |b| b.iter(|| lex_test()));
+    c.bench_function("osg_lex_speed", |b| b.iter(lex_test));
+
+    let project = make_project();
+    let cores = std::thread::available_parallelism().map_or(1, |jobs| jobs.get());
+
+    let single_job_project = project.clone();
+    c.bench_function("project_single_job", move |b| {
+        b.iter(|| project_test(&single_job_project, 1))
+    });
+    c.bench_function("project_all_jobs", move |b| b.iter(|| project_test(&project, cores)));
 }
 
 criterion_group!(benches, criterion_benchmark);
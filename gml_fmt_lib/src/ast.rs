@@ -0,0 +1,42 @@
+//! The syntax tree `parse` builds and `format_ast` prints.
+//!
+//! Every statement is a `StatementWrapper` and every expression an `ExprBoxInterior`,
+//! each with the byte `span` of source it was parsed from. Comments and newlines are
+//! kept in the tree, in the `CommentsAndNewlines` slots between the parts they sat
+//! between, so that printing a tree gives back every comment.
+//!
+//! `Scanner` is the tokenizer `parse` runs on, as an iterator over a source's tokens.
+//!
+//! `Statement`, `Expr` and `TokenType` may grow new variants as GML does, so
+//! matches on them need a wildcard arm.
+
+use serde::Serialize;
+
+pub use super::expressions::{CommentsAndNewlines, DSAccess, Expr, ExprBox, ExprBoxInterior};
+pub use super::lex_token::{Token, TokenType};
+pub use super::scanner::Scanner;
+pub use super::statements::{
+    Case, CaseType, DelimitedLine, DelimitedLines, Statement, StatementWrapper, StmtBox, VariableDecl,
+};
+
+/// A parsed file. It borrows the source it was parsed from, since every
+/// identifier, literal and comment in it points back into that source.
+///
+/// It serializes as its statements, each with its `span`, comments and tokens.
+#[derive(Debug, Serialize)]
+pub struct Ast<'a> {
+    pub statements: Vec<StmtBox<'a>>,
+    #[serde(skip)]
+    source: &'a str,
+}
+
+impl<'a> Ast<'a> {
+    pub(crate) fn new(statements: Vec<StmtBox<'a>>, source: &'a str) -> Ast<'a> {
+        Ast { statements, source }
+    }
+
+    /// The source this tree was parsed from.
+    pub fn source(&self) -> &'a str {
+        self.source
+    }
+}
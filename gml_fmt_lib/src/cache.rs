@@ -0,0 +1,79 @@
+use super::lang_config::LangConfig;
+use super::writer::write_atomically;
+use fnv::FnvHasher;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::hash::Hasher;
+use std::io;
+use std::path::Path;
+
+/// The default name of the file we keep the cache in, next to the files we format.
+pub const CACHE_FILE_NAME: &str = ".gml_fmt_cache";
+
+const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Hashes of files we know are already formatted. The whole cache is thrown out
+/// when gml_fmt's version or the `LangConfig` changes, since either could change
+/// what "formatted" means.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub(crate) struct Cache {
+    version: String,
+    config_hash: String,
+    files: BTreeMap<String, String>,
+}
+
+impl Cache {
+    /// Reads the cache at `path`. A missing, unreadable or out of date cache
+    /// just gives back an empty one. Files which have since been deleted or
+    /// renamed are dropped, so the cache doesn't grow forever.
+    pub(crate) fn load(path: &Path, lang_config: &LangConfig) -> Cache {
+        let config_hash = config_hash(lang_config);
+
+        if let Ok(contents) = fs::read_to_string(path) {
+            if let Ok(mut cache) = toml::from_str::<Cache>(&contents) {
+                if cache.version == VERSION && cache.config_hash == config_hash {
+                    cache.files.retain(|file, _| Path::new(file).is_file());
+                    return cache;
+                }
+            }
+        }
+
+        Cache {
+            version: VERSION.to_owned(),
+            config_hash,
+            files: BTreeMap::new(),
+        }
+    }
+
+    pub(crate) fn is_formatted(&self, this_file: &Path, contents: &str) -> bool {
+        self.files.get(&key(this_file)) == Some(&hash_contents(contents))
+    }
+
+    pub(crate) fn record(&mut self, this_file: &Path, contents_hash: String) {
+        self.files.insert(key(this_file), contents_hash);
+    }
+
+    pub(crate) fn save(&self, path: &Path) -> io::Result<()> {
+        let contents = toml::to_string(self).map_err(io::Error::other)?;
+        write_atomically(path, contents.as_bytes())
+    }
+}
+
+/// Hashes are kept as hex strings, since TOML can't hold every `u64`.
+pub(crate) fn hash_contents(contents: &str) -> String {
+    let mut hasher = FnvHasher::default();
+    hasher.write(contents.as_bytes());
+    format!("{:016x}", hasher.finish())
+}
+
+fn config_hash(lang_config: &LangConfig) -> String {
+    hash_contents(&toml::to_string(lang_config).unwrap_or_default())
+}
+
+fn key(this_file: &Path) -> String {
+    fs::canonicalize(this_file)
+        .unwrap_or_else(|_| this_file.to_path_buf())
+        .to_string_lossy()
+        .into_owned()
+}
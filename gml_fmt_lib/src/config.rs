@@ -1,72 +1,500 @@
-use bitflags;
-use std::path::PathBuf;
-use std::{ffi::OsStr, fs};
+use super::git::{self, GitChanges};
+use super::gmx::is_gmx_file;
+use super::lang_config::read_config_file;
+use super::project::{self, ResourceKind};
+use super::writer::Backup;
+use ignore::gitignore::Gitignore;
+use ignore::overrides::{Override, OverrideBuilder};
+use ignore::{Match, WalkBuilder};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
+use std::{error, ffi::OsStr, fmt, thread};
+
+/// A gitignore-syntax file listing paths we should never format.
+pub const IGNORE_FILE_NAME: &str = ".gml_fmt_ignore";
 
 pub struct Config {
     pub files: Vec<PathBuf>,
     pub print_flags: PrintFlags,
+    /// How many files we format at once. Defaults to the number of available cores.
+    pub jobs: usize,
+    /// Paths we couldn't read while walking the input directory. We skip them and carry on.
+    pub traversal_errors: Vec<TraversalError>,
+    /// If any are given, we only format the top-level statements on these lines.
+    /// Lines are 0-based and inclusive.
+    pub lines: Vec<RangeInclusive<u32>>,
+    /// Whether, and where, we copy a file before overwriting it.
+    pub backup: Backup,
+    /// Where we remember which files are already formatted, so we can skip them.
+    /// `None` turns the cache off.
+    pub cache: Option<PathBuf>,
+    /// Whether we check that formatting only changed whitespace before writing anything.
+    pub verify: bool,
+    input_path: PathBuf,
+    filters: FileFilters,
+    /// What GameMaker calls each file, when we found them through a `.yyp`.
+    resource_names: HashMap<PathBuf, String>,
 }
 
 impl Config {
-    pub fn new(input_path: PathBuf, print_flags: PrintFlags, do_file: bool) -> Result<Config, &'static str> {
+    pub fn new(input_path: PathBuf, print_flags: PrintFlags, do_file: bool) -> Result<Config, ConfigError> {
+        Config::with_filters(input_path, print_flags, do_file, FileFilters::default())
+    }
+
+    pub fn with_filters(
+        input_path: PathBuf,
+        print_flags: PrintFlags,
+        do_file: bool,
+        filters: FileFilters,
+    ) -> Result<Config, ConfigError> {
         let mut config = Config {
             files: Vec::new(),
             print_flags,
+            jobs: thread::available_parallelism().map_or(1, |jobs| jobs.get()),
+            traversal_errors: Vec::new(),
+            lines: Vec::new(),
+            backup: Backup::None,
+            cache: None,
+            verify: true,
+            input_path,
+            filters,
+            resource_names: HashMap::new(),
         };
 
-        if input_path.exists() == false {
-            return Err("Filepath given does not exist.");
+        if config.input_path.exists() == false {
+            return Err(ConfigError::PathNotFound(config.input_path));
         }
 
-        match (input_path.is_dir(), do_file) {
+        match (config.input_path.is_dir(), do_file) {
             (true, true) => {
-                return Err("Passed -f or --file but gave a directory filepath.");
+                return Err(ConfigError::ExpectedFile(config.input_path));
             }
 
             (true, false) => {
-                fn take_in_gml_files(directory_path: &PathBuf, config: &mut Config) {
-                    let gml_name = OsStr::new("gml");
-
-                    for entry in
-                        fs::read_dir(directory_path).expect(&format!("Error reading directory {:?}.", directory_path))
-                    {
-                        let entry = entry.expect(&format!("Error reading file"));
-                        let path = entry.path();
-
-                        if path.is_dir() == false {
-                            if path.extension() == Some(gml_name) {
-                                config.load_file_path(path);
-                            }
-                        } else {
-                            take_in_gml_files(&path, config);
-                        }
-                    }
-                }
-
-                take_in_gml_files(&input_path, &mut config);
+                config.take_in_gml_files()?;
             }
 
             (false, true) => {
-                config.load_file_path(input_path);
+                config.load_file_path(config.input_path.clone());
             }
 
             (false, false) => {
-                return Err("Did not pass -f but gave a filepath. Pass -f for files.");
+                return Err(ConfigError::ExpectedDirectory(config.input_path));
             }
         };
 
         Ok(config)
     }
 
+    /// The file or directory we were asked to format.
+    pub fn input_path(&self) -> &Path {
+        &self.input_path
+    }
+
     pub fn load_file_path(&mut self, path: PathBuf) {
         self.files.push(path);
     }
+
+    /// What GameMaker calls `path`, such as `obj_player: Step`, if we found it
+    /// through the project's `.yyp`.
+    pub fn resource_name(&self, path: &Path) -> Option<&str> {
+        self.resource_names.get(path).map(String::as_str)
+    }
+
+    /// Walks the input directory again, picking up any files which were added
+    /// or removed since we last looked. Does nothing when formatting a single file.
+    pub fn refresh(&mut self) -> Result<(), ConfigError> {
+        if self.input_path.is_dir() {
+            self.files.clear();
+            self.traversal_errors.clear();
+            self.resource_names.clear();
+            self.take_in_gml_files()?;
+        }
+
+        Ok(())
+    }
+
+    /// Finds `path`, however it's spelled, among our files, and gives back our spelling
+    /// of it. A file we haven't seen is taken in if walking again would pick it up, but
+    /// only that one path is checked. In a project, that means reading the `.yyp` again.
+    pub fn take_in_file(&mut self, path: &Path) -> Result<Option<PathBuf>, ConfigError> {
+        let input_path = git::canonical(&self.input_path);
+        let path = match git::canonical(path).strip_prefix(&input_path) {
+            Ok(relative) if relative.as_os_str().is_empty() => return Ok(Some(self.input_path.clone())),
+            Ok(relative) => self.input_path.join(relative),
+            Err(_) => return Ok(None),
+        };
+
+        if self.files.contains(&path) {
+            return Ok(Some(path));
+        }
+        if self.input_path.is_dir() == false || Config::is_gml_file(&path) == false || path.is_file() == false {
+            return Ok(None);
+        }
+
+        if let Some(yyp) = project::find_project_file(&self.input_path) {
+            self.files.clear();
+            self.resource_names.clear();
+            self.take_in_project_files(&yyp)?;
+            return Ok(self.files.contains(&path).then_some(path));
+        }
+
+        let include = self.filters.build_include(&self.input_path)?;
+        let exclude = self.filters.build_exclude(&self.input_path)?;
+        let mut ignore_files = IgnoreFiles::new(self.filters.respect_gitignore);
+        let in_git_dir = path
+            .ancestors()
+            .any(|ancestor| ancestor.file_name() == Some(OsStr::new(".git")));
+
+        if in_git_dir
+            || is_excluded(&exclude, &self.input_path, &path)
+            || ignore_files.is_ignored(&path, &mut self.traversal_errors)
+            || (include.is_empty() == false && include.matched(&path, false).is_whitelist() == false)
+        {
+            return Ok(None);
+        }
+
+        self.load_file_path(path.clone());
+        Ok(Some(path))
+    }
+
+    /// Drops every file git doesn't consider changed. Files which our filters
+    /// skipped stay skipped, even if git reports them.
+    pub fn retain_changed(&mut self, changes: &GitChanges) -> Result<(), ConfigError> {
+        let changed = git::changed_files(&self.input_path, changes)?;
+        self.files
+            .retain(|this_file| changed.contains(&git::canonical(this_file)));
+
+        Ok(())
+    }
+
+    /// Whether a path is one we would pick up when walking a directory: a `.gml`
+    /// file, or a GameMaker Studio 1.4 resource with code inside.
+    pub fn is_gml_file(path: &Path) -> bool {
+        path.extension() == Some(OsStr::new("gml")) || is_gmx_file(path)
+    }
+
+    /// Takes in the code a GameMaker project uses if the input directory holds a
+    /// `.yyp`, and every `.gml` file under it otherwise.
+    fn take_in_gml_files(&mut self) -> Result<(), ConfigError> {
+        match project::find_project_file(&self.input_path) {
+            Some(yyp) => self.take_in_project_files(&yyp),
+            None => self.walk_gml_files(),
+        }
+    }
+
+    /// Takes in the files the `.yyp` references, so anything the project has
+    /// dropped is left alone even though GameMaker leaves it on disk.
+    fn take_in_project_files(&mut self, yyp: &Path) -> Result<(), ConfigError> {
+        let include = self.filters.build_include(&self.input_path)?;
+        let exclude = self.filters.build_exclude(&self.input_path)?;
+        let mut ignore_files = IgnoreFiles::new(self.filters.respect_gitignore);
+        let (files, errors) = project::project_files(yyp)?;
+        self.traversal_errors.extend(errors);
+
+        for file in files {
+            if (self.filters.only.is_empty() || self.filters.only.contains(&file.kind))
+                && is_excluded(&exclude, &self.input_path, &file.path) == false
+                && ignore_files.is_ignored(&file.path, &mut self.traversal_errors) == false
+                && (include.is_empty() || include.matched(&file.path, false).is_whitelist())
+            {
+                self.resource_names.insert(file.path.clone(), file.name);
+                self.load_file_path(file.path);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn walk_gml_files(&mut self) -> Result<(), ConfigError> {
+        let include = self.filters.build_include(&self.input_path)?;
+        let exclude = self.filters.build_exclude(&self.input_path)?;
+        let respect_gitignore = self.filters.respect_gitignore;
+
+        // The walker keeps track of the directories above it when following
+        // links, and reports a loop rather than walking one forever.
+        let mut walker = WalkBuilder::new(&self.input_path);
+        walker
+            .standard_filters(false)
+            .follow_links(self.filters.follow_symlinks)
+            .parents(true)
+            .git_ignore(respect_gitignore)
+            .git_exclude(respect_gitignore)
+            .require_git(false)
+            .add_custom_ignore_filename(IGNORE_FILE_NAME)
+            .overrides(exclude)
+            .filter_entry(|entry| entry.file_name() != OsStr::new(".git"))
+            .sort_by_file_name(|a, b| a.cmp(b));
+
+        for entry in walker.build() {
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(e) => {
+                    self.traversal_errors
+                        .push(TraversalError::from_walk_error(e, &self.input_path));
+                    continue;
+                }
+            };
+            let is_dir = entry.file_type().is_some_and(|file_type| file_type.is_dir());
+
+            if entry.path_is_symlink() && entry.path().exists() == false {
+                self.traversal_errors
+                    .push(TraversalError::BrokenSymlink(entry.into_path()));
+                continue;
+            }
+
+            if is_dir == false
+                && Config::is_gml_file(entry.path())
+                && (include.is_empty() || include.matched(entry.path(), false).is_whitelist())
+            {
+                self.load_file_path(entry.into_path());
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Whether `path`, or a directory between it and `root`, matches an exclude glob.
+/// The walker never enters an excluded directory, so it only has to check each
+/// entry, but the files a `.yyp` lists come to us whole.
+fn is_excluded(exclude: &Override, root: &Path, path: &Path) -> bool {
+    path.ancestors()
+        .take_while(|ancestor| *ancestor != root)
+        .any(|ancestor| exclude.matched(ancestor, ancestor != path).is_ignore())
+}
+
+/// The `.gml_fmt_ignore` files, and the `.gitignore` files if we respect them, in the
+/// directories above files we didn't find by walking, such as the ones a `.yyp` lists.
+/// The walker reads these as it goes.
+struct IgnoreFiles {
+    respect_gitignore: bool,
+    /// Each directory's ignore files, strongest first. Most directories have none.
+    by_dir: HashMap<PathBuf, Vec<Gitignore>>,
+}
+
+impl IgnoreFiles {
+    fn new(respect_gitignore: bool) -> IgnoreFiles {
+        IgnoreFiles {
+            respect_gitignore,
+            by_dir: HashMap::new(),
+        }
+    }
+
+    /// Whether an ignore file skips `path`. As when walking, a deeper ignore file wins
+    /// over the ones above it, and `.gml_fmt_ignore` wins over `.gitignore`.
+    fn is_ignored(&mut self, path: &Path, errors: &mut Vec<TraversalError>) -> bool {
+        for dir in path.ancestors().skip(1) {
+            let respect_gitignore = self.respect_gitignore;
+            let ignores = self
+                .by_dir
+                .entry(dir.to_path_buf())
+                .or_insert_with(|| IgnoreFiles::read(dir, respect_gitignore, errors));
+
+            for ignore in ignores.iter() {
+                match ignore.matched_path_or_any_parents(path, false) {
+                    Match::Ignore(_) => return true,
+                    Match::Whitelist(_) => return false,
+                    Match::None => {}
+                }
+            }
+        }
+
+        false
+    }
+
+    fn read(dir: &Path, respect_gitignore: bool, errors: &mut Vec<TraversalError>) -> Vec<Gitignore> {
+        let mut names = vec![IGNORE_FILE_NAME];
+        if respect_gitignore {
+            names.push(".gitignore");
+        }
+
+        let mut ignores = Vec::new();
+        for name in names {
+            let path = dir.join(name);
+            if path.is_file() == false {
+                continue;
+            }
+
+            let (ignore, error) = Gitignore::new(&path);
+            if let Some(error) = error {
+                errors.push(TraversalError::from_walk_error(error, &path));
+            }
+            ignores.push(ignore);
+        }
+        ignores
+    }
+}
+
+/// Which files we take in when walking a directory. Globs use gitignore syntax and are
+/// relative to the directory being formatted.
+#[derive(Debug, Default, Clone, Deserialize)]
+pub struct FileFilters {
+    /// If any are given, only files matching one of these globs are formatted.
+    #[serde(default)]
+    pub include: Vec<String>,
+    /// Files and directories matching any of these globs are skipped.
+    #[serde(default)]
+    pub exclude: Vec<String>,
+    /// Skip anything the project's `.gitignore` files ignore.
+    #[serde(default)]
+    pub respect_gitignore: bool,
+    /// Walk into symlinked directories. Off by default.
+    #[serde(default)]
+    pub follow_symlinks: bool,
+    /// If any are given, only these kinds of resource are formatted. Only a project
+    /// with a `.yyp` knows which resource a file belongs to.
+    #[serde(default)]
+    pub only: Vec<ResourceKind>,
+}
+
+impl FileFilters {
+    /// Reads the `include`, `exclude`, `respect_gitignore`, `follow_symlinks` and `only` keys from the
+    /// same `gml_fmt.toml` that `LangConfig::load` reads, or gives back the defaults if there isn't one.
+    pub fn load(input_path: &PathBuf) -> Result<FileFilters, ConfigError> {
+        read_config_file(input_path)
+    }
+
+    fn build_include(&self, root: &Path) -> Result<Override, ConfigError> {
+        let mut builder = OverrideBuilder::new(root);
+        for glob in &self.include {
+            builder.add(glob).map_err(|e| ConfigError::bad_glob(glob, e))?;
+        }
+        builder.build().map_err(|e| ConfigError::bad_glob("", e))
+    }
+
+    fn build_exclude(&self, root: &Path) -> Result<Override, ConfigError> {
+        let mut builder = OverrideBuilder::new(root);
+        for glob in &self.exclude {
+            builder
+                .add(&format!("!{}", glob))
+                .map_err(|e| ConfigError::bad_glob(glob, e))?;
+        }
+        builder.build().map_err(|e| ConfigError::bad_glob("", e))
+    }
 }
 
+/// Something which stopped us finding the files to format, or reading how to format them.
+#[derive(Debug)]
+pub enum ConfigError {
+    /// The path we were given doesn't exist.
+    PathNotFound(PathBuf),
+    /// We were asked to format a single file, but were given a directory.
+    ExpectedFile(PathBuf),
+    /// We were given a file, but weren't asked to format a single file.
+    ExpectedDirectory(PathBuf),
+    /// An include or exclude glob couldn't be parsed.
+    BadGlob { glob: String, message: String },
+    /// A directory or file we needed couldn't be read.
+    Unreadable { path: PathBuf, message: String },
+    /// We asked git which files changed, and it couldn't tell us.
+    Git(String),
+    /// The `gml_fmt.toml` couldn't be parsed. Lines and columns start at 1.
+    BadConfigFile {
+        path: PathBuf,
+        line: Option<u32>,
+        column: Option<u32>,
+        message: String,
+    },
+}
+
+impl ConfigError {
+    fn bad_glob(glob: &str, error: ignore::Error) -> ConfigError {
+        ConfigError::BadGlob {
+            glob: glob.to_owned(),
+            message: error.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::PathNotFound(path) => write!(f, "Filepath {:?} does not exist.", path),
+            ConfigError::ExpectedFile(path) => {
+                write!(f, "Passed -f or --file but gave a directory filepath {:?}.", path)
+            }
+            ConfigError::ExpectedDirectory(path) => {
+                write!(f, "Did not pass -f but gave a filepath {:?}. Pass -f for files.", path)
+            }
+            ConfigError::BadGlob { glob, message } => write!(f, "Could not parse glob {:?}: {}", glob, message),
+            ConfigError::Unreadable { path, message } => write!(f, "Could not read {:?}: {}", path, message),
+            ConfigError::Git(message) => write!(f, "Could not ask git for changed files: {}", message),
+            ConfigError::BadConfigFile {
+                path,
+                line: Some(line),
+                column: Some(column),
+                message,
+            } => write!(f, "Could not parse {:?} at {}:{}: {}", path, line, column, message),
+            ConfigError::BadConfigFile { path, message, .. } => write!(f, "Could not parse {:?}: {}", path, message),
+        }
+    }
+}
+
+impl error::Error for ConfigError {}
+
+/// A path we skipped while finding the files to format. The rest are still formatted.
+#[derive(Debug)]
+pub enum TraversalError {
+    /// A directory or file couldn't be read.
+    Unreadable { path: PathBuf, message: String },
+    /// A symlink which points at nothing.
+    BrokenSymlink(PathBuf),
+    /// A symlinked directory which leads back into one of its own ancestors.
+    SymlinkLoop { path: PathBuf, ancestor: PathBuf },
+}
+
+impl TraversalError {
+    /// The walker wraps its errors in layers of paths and depths, so we dig out
+    /// the deepest path we can find to report.
+    fn from_walk_error(error: ignore::Error, fallback_path: &Path) -> TraversalError {
+        match error {
+            ignore::Error::Loop { ancestor, child } => TraversalError::SymlinkLoop { path: child, ancestor },
+            ignore::Error::WithDepth { err, .. } | ignore::Error::WithLineNumber { err, .. } => {
+                TraversalError::from_walk_error(*err, fallback_path)
+            }
+            ignore::Error::WithPath { path, err } => {
+                if path.is_symlink() && path.exists() == false {
+                    return TraversalError::BrokenSymlink(path);
+                }
+                TraversalError::from_walk_error(*err, &path)
+            }
+            ignore::Error::Partial(mut errors) if errors.len() == 1 => {
+                TraversalError::from_walk_error(errors.remove(0), fallback_path)
+            }
+            other => TraversalError::Unreadable {
+                path: fallback_path.to_path_buf(),
+                message: other.to_string(),
+            },
+        }
+    }
+}
+
+impl fmt::Display for TraversalError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TraversalError::Unreadable { path, message } => write!(f, "Could not read {:?}: {}", path, message),
+            TraversalError::BrokenSymlink(path) => write!(f, "Symlink {:?} points to nothing.", path),
+            TraversalError::SymlinkLoop { path, ancestor } => write!(
+                f,
+                "Symlink {:?} loops back to {:?}, so we did not follow it.",
+                path, ancestor
+            ),
+        }
+    }
+}
+
+impl error::Error for TraversalError {}
+
 bitflags::bitflags! {
     pub struct PrintFlags: u8 {
-        const OVERWRITE = 0b001;
-        const LOGS      = 0b010;
-        const LOG_AST   = 0b100;
+        const OVERWRITE = 0b0001;
+        /// Runs every file through the formatter, even ones the cache says are
+        /// formatted, so a `Reporter` hears about all of them.
+        const LOGS      = 0b0010;
+        /// Prints each file's syntax tree for `Reporter::on_formatted`.
+        const LOG_AST   = 0b0100;
     }
 }
@@ -0,0 +1,178 @@
+//! A lossless view of a source: every token, with the whitespace around it.
+//!
+//! The parser builds its syntax tree from a `Cst`, but where the tree drops
+//! whitespace and semicolons the formatter doesn't need, a `Cst` keeps every
+//! byte, so `cst.to_string()` always gives back the source it came from. That
+//! makes it the thing to edit when a tool wants to change a few tokens and
+//! leave the rest of a file byte-for-byte as it was.
+//!
+//! Newlines and comments are tokens of their own. The trivia on a token is the
+//! spaces, tabs and `\r`s after it, so a newline's trivia is the indentation of
+//! the next line, which is where the parser reads it from.
+
+use super::lex_token::{Token, TokenType};
+use super::scanner::Scanner;
+use std::fmt;
+use std::rc::Rc;
+
+/// A token and the source around it.
+#[derive(Debug, Clone)]
+pub struct CstToken<'a> {
+    pub token: Token<'a>,
+    /// The source the token was scanned from.
+    pub text: &'a str,
+    /// Whitespace before the token which no earlier token owns. Only the first
+    /// token of a file has any.
+    pub leading_trivia: &'a str,
+    /// Whitespace after the token, up to the next one.
+    pub trailing_trivia: &'a str,
+}
+
+impl fmt::Display for CstToken<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}{}", self.leading_trivia, self.text, self.trailing_trivia)
+    }
+}
+
+/// Every token of a source, in order, with the whitespace between them.
+#[derive(Debug, Clone)]
+pub struct Cst<'a> {
+    tokens: Vec<CstToken<'a>>,
+    /// A source with no tokens at all is all whitespace, which we keep here.
+    trivia: &'a str,
+}
+
+impl<'a> Cst<'a> {
+    pub fn new(source: &'a str) -> Cst<'a> {
+        let mut tokens: Vec<CstToken<'a>> = Vec::new();
+        let mut last_end = 0;
+
+        for mut token in Scanner::new(source) {
+            let start = token.span.start as usize;
+            // The scanner counts the next line's indentation into a newline, but it's trivia.
+            let end = match token.token_type {
+                TokenType::Newline(_) => start + 1,
+                _ => token.span.end as usize,
+            };
+            token.span.end = end as u32;
+            let gap = &source[last_end..start];
+
+            let leading_trivia = match tokens.last_mut() {
+                Some(previous) => {
+                    previous.trailing_trivia = gap;
+                    ""
+                }
+                None => gap,
+            };
+
+            tokens.push(CstToken {
+                text: &source[start..end],
+                token,
+                leading_trivia,
+                trailing_trivia: "",
+            });
+            last_end = end;
+        }
+
+        let rest = &source[last_end..];
+        let trivia = match tokens.last_mut() {
+            Some(last) => {
+                last.trailing_trivia = rest;
+                ""
+            }
+            None => rest,
+        };
+
+        Cst { tokens, trivia }
+    }
+
+    pub fn tokens(&self) -> &[CstToken<'a>] {
+        &self.tokens
+    }
+
+    /// The index of the token whose text covers `offset`, if any does.
+    pub fn token_at(&self, offset: u32) -> Option<usize> {
+        let index = self.tokens.partition_point(|t| t.token.span.end <= offset);
+        self.tokens
+            .get(index)
+            .filter(|t| t.token.span.start <= offset)
+            .map(|_| index)
+    }
+
+    /// Prints the source back out with the text of some tokens, given by their
+    /// index, replaced. Everything else, trivia included, is copied as it was.
+    pub fn replace_tokens<'r>(&self, replacements: impl IntoIterator<Item = (usize, &'r str)>) -> String {
+        let mut replacements: Vec<_> = replacements.into_iter().collect();
+        replacements.sort_by_key(|&(index, _)| index);
+        let mut replacements = replacements.into_iter().peekable();
+
+        let mut output = String::from(self.trivia);
+        for (index, cst_token) in self.tokens.iter().enumerate() {
+            let mut text = cst_token.text;
+            while let Some((_, replacement)) = replacements.next_if(|&(i, _)| i == index) {
+                text = replacement;
+            }
+
+            output.push_str(cst_token.leading_trivia);
+            output.push_str(text);
+            output.push_str(cst_token.trailing_trivia);
+        }
+
+        output
+    }
+
+    /// The tokens, cheap to clone so the parser can rewind to an earlier one.
+    pub(crate) fn into_token_stream(self) -> TokenStream<'a> {
+        let tokens = self.tokens.into_iter().map(|cst_token| {
+            let mut token = cst_token.token;
+            if let TokenType::Newline(_) = token.token_type {
+                token.token_type = TokenType::Newline(indentation(cst_token.trailing_trivia));
+            }
+            token
+        });
+
+        TokenStream {
+            tokens: tokens.collect(),
+            position: 0,
+        }
+    }
+}
+
+/// How far a line is indented, in steps of four spaces. A tab is a whole step.
+fn indentation(trivia: &str) -> usize {
+    let width: usize = trivia
+        .chars()
+        .map_while(|c| match c {
+            ' ' => Some(1),
+            '\t' => Some(4),
+            _ => None,
+        })
+        .sum();
+    width / 4
+}
+
+impl fmt::Display for Cst<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.trivia)?;
+        for token in &self.tokens {
+            write!(f, "{}", token)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct TokenStream<'a> {
+    tokens: Rc<[Token<'a>]>,
+    position: usize,
+}
+
+impl<'a> Iterator for TokenStream<'a> {
+    type Item = Token<'a>;
+
+    fn next(&mut self) -> Option<Token<'a>> {
+        let token = self.tokens.get(self.position)?.clone();
+        self.position += 1;
+        Some(token)
+    }
+}
@@ -0,0 +1,187 @@
+use super::error::Error;
+use super::lex_token::Token;
+use super::line_index::LineIndex;
+use serde::Serialize;
+use std::fmt::{self, Write};
+use std::ops::Range;
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// A problem we found in a file, with enough information to point at it.
+/// Lines and columns start at 1; the span is a byte range into the source.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub message: String,
+    pub line: u32,
+    pub column: u32,
+    pub span: Range<u32>,
+    pub help: Option<String>,
+}
+
+impl Diagnostic {
+    /// An error pointing at `token` in the source `lines` was built from.
+    pub(crate) fn error_at(lines: &LineIndex, token: &Token, message: String) -> Diagnostic {
+        let (line, column) = lines.line_col(token.span.start);
+
+        Diagnostic {
+            severity: Severity::Error,
+            message,
+            line: line + 1,
+            column: column + 1,
+            span: token.span.clone(),
+            help: None,
+        }
+    }
+
+    /// An error pointing just past the last thing written in the source `lines` was built from.
+    pub(crate) fn error_at_end(lines: &LineIndex, message: String) -> Diagnostic {
+        let written = lines.source().trim_end().len() as u32;
+        let (line, column) = lines.line_col(written);
+
+        Diagnostic {
+            severity: Severity::Error,
+            message,
+            line: line + 1,
+            column: column + 1,
+            span: written..written,
+            help: None,
+        }
+    }
+
+    pub(crate) fn with_help(mut self, help: &str) -> Diagnostic {
+        self.help = Some(help.to_owned());
+        self
+    }
+
+    /// Renders the diagnostic like rustc does, with the offending line and a
+    /// caret underline beneath the problem.
+    ///
+    /// ```text
+    /// error: expected `case` or `default`, found `x`
+    ///  --> scripts/player_step.gml:1:14
+    ///   |
+    /// 1 | switch (a) { x = 2; }
+    ///   |              ^
+    /// ```
+    pub fn render(&self, source: &str, path: Option<&Path>) -> String {
+        let line_number = self.line.to_string();
+        let gutter = " ".repeat(line_number.len());
+        let mut output = String::new();
+
+        let _ = writeln!(output, "{}: {}", self.severity, self.message);
+        match path {
+            Some(path) => {
+                let _ = writeln!(output, "{}--> {}:{}:{}", gutter, path.display(), self.line, self.column);
+            }
+            None => {
+                let _ = writeln!(output, "{}--> {}:{}", gutter, self.line, self.column);
+            }
+        }
+
+        let span = self.span.start as usize..self.span.end as usize;
+        let line_start = source[..span.start.min(source.len())].rfind('\n').map_or(0, |i| i + 1);
+        let line_end = source[line_start..].find('\n').map_or(source.len(), |i| line_start + i);
+        let source_line = source[line_start..line_end].trim_end_matches('\r');
+
+        let start = span.start.clamp(line_start, line_start + source_line.len());
+        let end = span.end.clamp(start, line_start + source_line.len());
+
+        // Tabs are kept so the carets line up however wide the terminal draws them.
+        let padding: String = source[line_start..start]
+            .chars()
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+        let carets = "^".repeat(source[start..end].chars().count().max(1));
+
+        let _ = writeln!(output, "{} |", gutter);
+        let _ = writeln!(output, "{} | {}", line_number, source_line);
+        let _ = writeln!(output, "{} | {}{}", gutter, padding, carets);
+        if let Some(help) = &self.help {
+            let _ = writeln!(output, "{} = help: {}", gutter, help);
+        }
+
+        output
+    }
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: {} at {}:{}",
+            self.severity, self.message, self.line, self.column
+        )
+    }
+}
+
+impl std::error::Error for Diagnostic {}
+
+/// Every problem `parse` found in a file, in the order they appear.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostics(pub Vec<Diagnostic>);
+
+impl Diagnostics {
+    /// Renders every diagnostic, one after another, like `Diagnostic::render`.
+    pub fn render(&self, source: &str, path: Option<&Path>) -> String {
+        self.0
+            .iter()
+            .map(|diagnostic| diagnostic.render(source, path))
+            .collect()
+    }
+}
+
+impl std::ops::Deref for Diagnostics {
+    type Target = [Diagnostic];
+
+    fn deref(&self) -> &[Diagnostic] {
+        &self.0
+    }
+}
+
+impl IntoIterator for Diagnostics {
+    type Item = Diagnostic;
+    type IntoIter = std::vec::IntoIter<Diagnostic>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+impl fmt::Display for Diagnostics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, diagnostic) in self.0.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{}", diagnostic)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for Diagnostics {}
+
+/// Describes an error from `run` for a person, with source snippets for any diagnostics.
+pub fn render_error(error: &Error, source: &str, path: Option<&Path>) -> String {
+    match error {
+        Error::Parse { diagnostics, .. } => diagnostics.render(source, path),
+        Error::Verification { diagnostic, .. } => diagnostic.render(source, path),
+        _ => format!("{}\n", error),
+    }
+}
@@ -0,0 +1,126 @@
+use std::fmt::Write;
+
+/// How many unchanged lines we show around each change.
+const CONTEXT: usize = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Change {
+    Same,
+    Removed,
+    Added,
+}
+
+/// A unified diff from `before` to `after`, or `None` if they're the same.
+pub(crate) fn unified_diff(before: &str, after: &str, before_name: &str, after_name: &str) -> Option<String> {
+    if before == after {
+        return None;
+    }
+
+    // Splitting on '\n' rather than using `lines` keeps a change to the final newline visible.
+    let (before, after) = match (before.strip_suffix('\n'), after.strip_suffix('\n')) {
+        (Some(before), Some(after)) => (before, after),
+        _ => (before, after),
+    };
+    let old: Vec<&str> = before.split('\n').collect();
+    let new: Vec<&str> = after.split('\n').collect();
+    let lines = diff_lines(&old, &new);
+
+    let changed: Vec<usize> = (0..lines.len()).filter(|&i| lines[i].0 != Change::Same).collect();
+    let mut output = format!("--- {}\n+++ {}\n", before_name, after_name);
+
+    let mut next_change = 0;
+    while next_change < changed.len() {
+        let start = changed[next_change].saturating_sub(CONTEXT);
+        let mut end = (changed[next_change] + CONTEXT + 1).min(lines.len());
+        next_change += 1;
+
+        // Changes close enough to share context go in the same hunk.
+        while next_change < changed.len() && changed[next_change] <= end + CONTEXT {
+            end = (changed[next_change] + CONTEXT + 1).min(lines.len());
+            next_change += 1;
+        }
+
+        write_hunk(&mut output, &lines, start, end);
+    }
+
+    Some(output)
+}
+
+fn write_hunk(output: &mut String, lines: &[(Change, &str)], start: usize, end: usize) {
+    let lines_before = |change: Change| lines[..start].iter().filter(|(c, _)| *c != change).count();
+    let old_start = lines_before(Change::Added);
+    let new_start = lines_before(Change::Removed);
+
+    let hunk = &lines[start..end];
+    let old_len = hunk.iter().filter(|(c, _)| *c != Change::Added).count();
+    let new_len = hunk.iter().filter(|(c, _)| *c != Change::Removed).count();
+
+    let _ = writeln!(
+        output,
+        "@@ -{},{} +{},{} @@",
+        old_start + (old_len > 0) as usize,
+        old_len,
+        new_start + (new_len > 0) as usize,
+        new_len
+    );
+
+    for (change, line) in hunk {
+        let marker = match change {
+            Change::Same => ' ',
+            Change::Removed => '-',
+            Change::Added => '+',
+        };
+        let _ = writeln!(output, "{}{}", marker, line);
+    }
+}
+
+/// Lines in order, each marked as kept, removed or added, following the longest
+/// common subsequence of the two. Formatting rarely changes much, so we trim
+/// what's shared at either end before building the table.
+fn diff_lines<'a>(old: &[&'a str], new: &[&'a str]) -> Vec<(Change, &'a str)> {
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    let old_middle = &old[prefix..old.len() - suffix];
+    let new_middle = &new[prefix..new.len() - suffix];
+
+    // `common[i * width + j]` is the length of the longest common subsequence
+    // of `old_middle[i..]` and `new_middle[j..]`.
+    let width = new_middle.len() + 1;
+    let mut common = vec![0u32; (old_middle.len() + 1) * width];
+    for i in (0..old_middle.len()).rev() {
+        for j in (0..new_middle.len()).rev() {
+            common[i * width + j] = if old_middle[i] == new_middle[j] {
+                common[(i + 1) * width + j + 1] + 1
+            } else {
+                common[(i + 1) * width + j].max(common[i * width + j + 1])
+            };
+        }
+    }
+
+    let mut lines: Vec<_> = old[..prefix].iter().map(|line| (Change::Same, *line)).collect();
+    let (mut i, mut j) = (0, 0);
+    while i < old_middle.len() && j < new_middle.len() {
+        if old_middle[i] == new_middle[j] {
+            lines.push((Change::Same, old_middle[i]));
+            i += 1;
+            j += 1;
+        } else if common[(i + 1) * width + j] >= common[i * width + j + 1] {
+            lines.push((Change::Removed, old_middle[i]));
+            i += 1;
+        } else {
+            lines.push((Change::Added, new_middle[j]));
+            j += 1;
+        }
+    }
+    lines.extend(old_middle[i..].iter().map(|line| (Change::Removed, *line)));
+    lines.extend(new_middle[j..].iter().map(|line| (Change::Added, *line)));
+    lines.extend(old[old.len() - suffix..].iter().map(|line| (Change::Same, *line)));
+
+    lines
+}
@@ -0,0 +1,109 @@
+use super::config::ConfigError;
+use super::diagnostic::{Diagnostic, Diagnostics};
+use std::path::{Path, PathBuf};
+use std::{error, fmt, io};
+
+/// Everything which can go wrong in the library. Errors about one file carry
+/// its `path`, which is `None` when we were handed source rather than a file.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// A file couldn't be read or written.
+    Io { path: Option<PathBuf>, source: io::Error },
+    /// The source couldn't be parsed. There's always at least one diagnostic.
+    Parse {
+        path: Option<PathBuf>,
+        diagnostics: Diagnostics,
+    },
+    /// We couldn't work out which files to format, or how to format them.
+    Config(ConfigError),
+    /// Formatting would have changed more than whitespace, so nothing was written.
+    Verification {
+        path: Option<PathBuf>,
+        diagnostic: Diagnostic,
+    },
+    /// Formatting the output a second time changed it again, as `diff` shows.
+    Unstable { path: Option<PathBuf>, diff: String },
+    /// Some files in a run couldn't be formatted. Each error is about one of them.
+    Files(Vec<Error>),
+}
+
+impl Error {
+    pub(crate) fn io(path: &Path, source: io::Error) -> Error {
+        Error::Io {
+            path: Some(path.to_path_buf()),
+            source,
+        }
+    }
+
+    /// The file this error is about, if it's about one.
+    pub fn path(&self) -> Option<&Path> {
+        match self {
+            Error::Io { path, .. }
+            | Error::Parse { path, .. }
+            | Error::Verification { path, .. }
+            | Error::Unstable { path, .. } => path.as_deref(),
+            Error::Config(_) | Error::Files(_) => None,
+        }
+    }
+
+    /// The same error, about `file`.
+    pub(crate) fn in_file(mut self, file: &Path) -> Error {
+        if let Error::Io { path, .. }
+        | Error::Parse { path, .. }
+        | Error::Verification { path, .. }
+        | Error::Unstable { path, .. } = &mut self
+        {
+            *path = Some(file.to_path_buf());
+        }
+        self
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(path) = self.path() {
+            write!(f, "{:?}: ", path)?;
+        }
+
+        match self {
+            Error::Io { source, .. } => write!(f, "{}", source),
+            Error::Parse { diagnostics, .. } => write!(f, "{}", diagnostics),
+            Error::Config(e) => write!(f, "{}", e),
+            Error::Verification { diagnostic, .. } => write!(f, "{}", diagnostic),
+            Error::Unstable { .. } => write!(f, "formatting it a second time changed it again"),
+            Error::Files(errors) => {
+                write!(f, "Could not format {} file(s):", errors.len())?;
+                for e in errors {
+                    write!(f, "\n    {}", e)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Error::Io { source, .. } => Some(source),
+            Error::Config(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<ConfigError> for Error {
+    fn from(e: ConfigError) -> Error {
+        Error::Config(e)
+    }
+}
+
+impl From<Diagnostics> for Error {
+    fn from(diagnostics: Diagnostics) -> Error {
+        Error::Parse {
+            path: None,
+            diagnostics,
+        }
+    }
+}
@@ -1,16 +1,23 @@
 use super::lex_token::*;
 use super::statements::DelimitedLines;
+use serde::Serialize;
+use std::ops::Range;
+
 pub type ExprBox<'a> = Box<ExprBoxInterior<'a>>;
 pub type CommentsAndNewlines<'a> = Option<Vec<Token<'a>>>;
 pub type DSAccess<'a> = Vec<(CommentsAndNewlines<'a>, ExprBox<'a>)>;
 
-#[derive(Debug)]
+/// An expression, with the comments and newlines written straight after it.
+#[derive(Debug, Serialize)]
 pub struct ExprBoxInterior<'a> {
     pub expr: Expr<'a>,
     pub trailing_comments: CommentsAndNewlines<'a>,
+    /// The bytes of the source the expression was written in, not counting its trailing comments.
+    pub span: Range<u32>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
+#[non_exhaustive]
 pub enum Expr<'a> {
     Call {
         procedure_name: ExprBox<'a>,
@@ -0,0 +1,215 @@
+use super::config::{Config, ConfigError, PrintFlags};
+use super::error::Error;
+use super::lang_config::LangConfig;
+use super::reporter::{Reporter, SkipReason};
+use super::verify::verify_tokens;
+use super::writer::write_formatted;
+use super::Formatted;
+use std::collections::HashSet;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+/// Which files git should tell us have changed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GitChanges {
+    /// Files modified or staged since `HEAD`, plus untracked files.
+    Uncommitted,
+    /// Files changed since the given revision, plus untracked files.
+    Since(String),
+    /// Files in the index which differ from `HEAD`.
+    Staged,
+}
+
+/// Asks git for the files which have changed in the repository holding `input_path`.
+/// The paths we give back are canonical, so they can be compared against walked paths.
+pub(crate) fn changed_files(input_path: &Path, changes: &GitChanges) -> Result<HashSet<PathBuf>, ConfigError> {
+    let dir = git_dir_for(input_path);
+    let top_level = PathBuf::from(git(&dir, &["rev-parse", "--show-toplevel"])?.trim_end());
+
+    let mut names = match changes {
+        GitChanges::Uncommitted => {
+            if git(&dir, &["rev-parse", "--verify", "--quiet", "HEAD"]).is_ok() {
+                git(&dir, &["diff", "--name-only", "-z", "--diff-filter=ACMR", "HEAD"])?
+            } else {
+                // Nothing has been committed yet, so everything in the index is new.
+                git(&dir, &["ls-files", "-z", "--full-name"])?
+            }
+        }
+        GitChanges::Since(rev) => git(&dir, &["diff", "--name-only", "-z", "--diff-filter=ACMR", rev, "--"])?,
+        GitChanges::Staged => git(&dir, &["diff", "--cached", "--name-only", "-z", "--diff-filter=ACMR"])?,
+    };
+
+    if let GitChanges::Uncommitted | GitChanges::Since(_) = changes {
+        names.push_str(&git(
+            &dir,
+            &["ls-files", "-z", "--full-name", "--others", "--exclude-standard"],
+        )?);
+    }
+
+    Ok(names
+        .split('\0')
+        .filter(|name| name.is_empty() == false)
+        .map(|name| canonical(&top_level.join(name)))
+        .collect())
+}
+
+/// Formats the staged content of every file in `config`, rather than the
+/// working tree, and stages the result. If the working tree copy matches what
+/// was staged, it is overwritten too, so the file doesn't show up as modified.
+pub fn format_staged(config: &Config, lang_config: &LangConfig, reporter: &mut dyn Reporter) -> Result<(), Error> {
+    let overwrite = config.print_flags.contains(PrintFlags::OVERWRITE);
+
+    for this_file in &config.files {
+        let dir = git_dir_for(this_file);
+        // Git runs in the file's folder, so the pathspec is just its name.
+        let file_name = match this_file.file_name() {
+            Some(file_name) => file_name.to_string_lossy(),
+            None => continue,
+        };
+
+        // Each line is "<mode> <object> <stage>\t<path>", with the path relative to the top level,
+        // which is also what `update-index --cacheinfo` wants.
+        let entry = git(&dir, &["ls-files", "-s", "-z", "--full-name", "--", &file_name])?;
+        let (info, repo_path) = match entry.trim_end_matches('\0').split_once('\t') {
+            Some(parts) => parts,
+            None => {
+                reporter.on_skipped(this_file, SkipReason::NotStaged);
+                continue;
+            }
+        };
+        let mut info = info.split(' ');
+        let (mode, object) = match (info.next(), info.next()) {
+            (Some(mode), Some(object)) => (mode, object),
+            _ => {
+                reporter.on_skipped(this_file, SkipReason::NotStaged);
+                continue;
+            }
+        };
+
+        // Like `fs::read_to_string`, we won't touch a file that isn't UTF-8, since
+        // staging a lossy copy of it would corrupt it.
+        let staged = match String::from_utf8(git_bytes(&dir, &["cat-file", "blob", object])?) {
+            Ok(staged) => staged,
+            Err(e) => {
+                let error = Error::io(this_file, io::Error::new(io::ErrorKind::InvalidData, e.utf8_error()));
+                reporter.on_error(this_file, None, &error);
+                continue;
+            }
+        };
+        if super::ignores_file(this_file, &staged) {
+            reporter.on_skipped(this_file, SkipReason::Ignored);
+            continue;
+        }
+
+        reporter.on_file_start(this_file, &staged);
+        let result = if super::is_gmx_file(this_file) {
+            super::format_gmx(&staged, lang_config, config.verify)
+        } else {
+            super::run(&staged, lang_config, None)
+        };
+        let formatted = match result {
+            Ok(output) => Formatted {
+                output,
+                diagnostics: Vec::new(),
+            },
+            Err(e) => {
+                reporter.on_error(this_file, Some(&staged), &e.in_file(this_file));
+                continue;
+            }
+        };
+        reporter.on_formatted(this_file, &staged, &formatted, None);
+        let output = formatted.output;
+
+        if overwrite == false || output == staged {
+            continue;
+        }
+
+        // `format_gmx` verifies each piece of code itself.
+        if config.verify && super::is_gmx_file(this_file) == false {
+            if let Err(diagnostic) = verify_tokens(&staged, &output) {
+                let error = Error::Verification {
+                    path: Some(this_file.clone()),
+                    diagnostic,
+                };
+                reporter.on_error(this_file, Some(&staged), &error);
+                continue;
+            }
+        }
+
+        let new_object = git_with_input(&dir, &["hash-object", "-w", "--stdin"], &output)?;
+        let cache_info = format!("{},{},{}", mode, new_object.trim_end(), repo_path);
+        git(&dir, &["update-index", "--cacheinfo", &cache_info])?;
+
+        if fs::read_to_string(this_file).is_ok_and(|working| working == staged) {
+            write_formatted(this_file, &staged, &output, &config.backup).map_err(|e| Error::io(this_file, e))?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Git needs to run inside the repository, so for a single file we run it in its folder.
+fn git_dir_for(path: &Path) -> PathBuf {
+    if path.is_dir() {
+        return path.to_path_buf();
+    }
+
+    match path.parent() {
+        Some(parent) if parent.as_os_str().is_empty() == false => parent.to_path_buf(),
+        _ => PathBuf::from("."),
+    }
+}
+
+fn git(dir: &Path, args: &[&str]) -> Result<String, ConfigError> {
+    git_bytes(dir, args).map(|stdout| String::from_utf8_lossy(&stdout).into_owned())
+}
+
+/// Runs git and gives back exactly what it printed, for output which may not be text.
+fn git_bytes(dir: &Path, args: &[&str]) -> Result<Vec<u8>, ConfigError> {
+    let output = Command::new("git")
+        .current_dir(dir)
+        .args(args)
+        .output()
+        .map_err(|e| ConfigError::Git(format!("could not run git: {}", e)))?;
+
+    git_output(output)
+}
+
+fn git_with_input(dir: &Path, args: &[&str], input: &str) -> Result<String, ConfigError> {
+    let mut child = Command::new("git")
+        .current_dir(dir)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| ConfigError::Git(format!("could not run git: {}", e)))?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin
+            .write_all(input.as_bytes())
+            .map_err(|e| ConfigError::Git(format!("could not write to git: {}", e)))?;
+    }
+
+    let output = child
+        .wait_with_output()
+        .map_err(|e| ConfigError::Git(format!("could not run git: {}", e)))?;
+
+    git_output(output).map(|stdout| String::from_utf8_lossy(&stdout).into_owned())
+}
+
+fn git_output(output: std::process::Output) -> Result<Vec<u8>, ConfigError> {
+    if output.status.success() {
+        Ok(output.stdout)
+    } else {
+        Err(ConfigError::Git(
+            String::from_utf8_lossy(&output.stderr).trim().to_owned(),
+        ))
+    }
+}
+
+pub(crate) fn canonical(path: &Path) -> PathBuf {
+    fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
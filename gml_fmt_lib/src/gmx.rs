@@ -0,0 +1,251 @@
+//! Formats the GML which GameMaker Studio 1.4 keeps inside its XML resource files.
+//!
+//! Objects and timelines hold their code as "Execute code" actions, whose only
+//! argument is the code as an XML string. Rooms hold their creation code in a
+//! `<code>` element, and each instance's in a `code` attribute. We find each
+//! piece, format it on its own, and put it back escaped, copying every other
+//! byte of the file as it was.
+
+use super::diagnostic::{Diagnostic, Diagnostics};
+use super::error::Error;
+use super::lang_config::LangConfig;
+use super::line_index::LineIndex;
+use super::verify::verify_tokens;
+use std::ops::Range;
+use std::path::Path;
+
+/// The resource files which can hold code.
+const GMX_SUFFIXES: [&str; 3] = [".object.gmx", ".room.gmx", ".timeline.gmx"];
+
+/// Whether `path` is a GameMaker Studio 1.4 object, room or timeline.
+pub fn is_gmx_file(path: &Path) -> bool {
+    path.file_name()
+        .map(|name| name.to_string_lossy())
+        .is_some_and(|name| GMX_SUFFIXES.iter().any(|suffix| name.ends_with(suffix)))
+}
+
+/// Formats every piece of GML in the GameMaker Studio 1.4 XML `xml`, leaving the
+/// XML around it untouched. Code which asks to be ignored with `// @gml_fmt ignore`
+/// is left as it was. If any piece can't be parsed, we fail with every problem in
+/// all of them, and when `verify` is set, with the first piece which formatting
+/// would change more than whitespace. Diagnostics point into `xml`.
+pub fn format_gmx(xml: &str, lang_config: &LangConfig, verify: bool) -> Result<String, Error> {
+    let xml_lines = LineIndex::new(xml);
+    let mut output = String::with_capacity(xml.len());
+    let mut last_end = 0;
+    let mut diagnostics = Vec::new();
+
+    for code in embedded_code(xml) {
+        if code.text.contains("// @gml_fmt ignore") {
+            continue;
+        }
+
+        let formatted = match super::run(&code.text, lang_config, None) {
+            Ok(formatted) => code.keep_layout(formatted),
+            Err(Error::Parse {
+                diagnostics: Diagnostics(found),
+                ..
+            }) => {
+                diagnostics.extend(found.into_iter().map(|d| code.in_xml(d, &xml_lines)));
+                continue;
+            }
+            Err(e) => return Err(e),
+        };
+
+        if formatted == code.text {
+            continue;
+        }
+
+        if verify {
+            verify_tokens(&code.text, &formatted).map_err(|diagnostic| Error::Verification {
+                path: None,
+                diagnostic: code.in_xml(diagnostic, &xml_lines),
+            })?;
+        }
+
+        output.push_str(&xml[last_end..code.raw.start]);
+        output.push_str(&escape(&formatted, code.in_attribute));
+        last_end = code.raw.end;
+    }
+
+    if diagnostics.is_empty() == false {
+        return Err(Diagnostics(diagnostics).into());
+    }
+
+    output.push_str(&xml[last_end..]);
+    Ok(output)
+}
+
+/// One piece of GML inside the XML.
+struct EmbeddedCode {
+    /// Where the escaped code sits in the XML.
+    raw: Range<usize>,
+    /// The code, unescaped.
+    text: String,
+    /// For each byte of `text`, and one past its end, where it came from in `raw`.
+    offsets: Vec<usize>,
+    in_attribute: bool,
+}
+
+impl EmbeddedCode {
+    fn new(xml: &str, raw: Range<usize>, in_attribute: bool) -> EmbeddedCode {
+        let (text, offsets) = unescape(&xml[raw.clone()]);
+        EmbeddedCode {
+            raw,
+            text,
+            offsets,
+            in_attribute,
+        }
+    }
+
+    /// GameMaker doesn't end code with a newline as we do for files, and on
+    /// Windows it writes `\r\n`, so we keep the code's own ending and line breaks.
+    fn keep_layout(&self, formatted: String) -> String {
+        let mut formatted = formatted.trim_end().replace("\r\n", "\n");
+        if self.text.contains("\r\n") {
+            formatted = formatted.replace('\n', "\r\n");
+        }
+
+        formatted.push_str(&self.text[self.text.trim_end().len()..]);
+        formatted
+    }
+
+    /// Moves a diagnostic about the code onto the XML it came from.
+    fn in_xml(&self, mut diagnostic: Diagnostic, xml_lines: &LineIndex) -> Diagnostic {
+        let to_xml =
+            |offset: u32| (self.raw.start + self.offsets[(offset as usize).min(self.offsets.len() - 1)]) as u32;
+        diagnostic.span = to_xml(diagnostic.span.start)..to_xml(diagnostic.span.end);
+
+        let (line, column) = xml_lines.line_col(diagnostic.span.start);
+        diagnostic.line = line + 1;
+        diagnostic.column = column + 1;
+        diagnostic
+    }
+}
+
+/// Every piece of code in the XML, in order. GameMaker writes these files itself,
+/// always the same way, so we look for its tags rather than parse the XML.
+fn embedded_code(xml: &str) -> Vec<EmbeddedCode> {
+    let mut code = Vec::new();
+
+    for action in elements(xml, "action") {
+        let body = &xml[action.clone()];
+        // "Execute code" is action 603 in the main library.
+        if body.contains("<libid>1</libid>") == false || body.contains("<id>603</id>") == false {
+            continue;
+        }
+
+        let arguments = match elements(body, "arguments").next() {
+            Some(arguments) => arguments,
+            None => continue,
+        };
+        if let Some(string) = elements(&body[arguments.clone()], "string").next() {
+            let start = action.start + arguments.start;
+            code.push(EmbeddedCode::new(xml, start + string.start..start + string.end, false));
+        }
+    }
+
+    // Room creation code.
+    for element in elements(xml, "code") {
+        code.push(EmbeddedCode::new(xml, element, false));
+    }
+
+    // Instance creation code.
+    for (tag_start, _) in xml.match_indices("<instance ") {
+        let tag_end = xml[tag_start..].find('>').map_or(xml.len(), |end| tag_start + end);
+        let tag = &xml[tag_start..tag_end];
+
+        if let Some(attribute) = tag.find(" code=\"") {
+            let start = tag_start + attribute + " code=\"".len();
+            let end = xml[start..tag_end].find('"').map_or(tag_end, |end| start + end);
+            code.push(EmbeddedCode::new(xml, start..end, true));
+        }
+    }
+
+    code.sort_by_key(|code| code.raw.start);
+    code
+}
+
+/// The contents of each `<name>...</name>` element in `xml`. Empty `<name/>`
+/// elements have nothing in them to format, so we skip them.
+fn elements<'a>(xml: &'a str, name: &str) -> impl Iterator<Item = Range<usize>> + 'a {
+    let open = format!("<{}>", name);
+    let close = format!("</{}>", name);
+    let mut position = 0;
+
+    std::iter::from_fn(move || {
+        let start = position + xml[position..].find(&open)? + open.len();
+        let end = start + xml[start..].find(&close)?;
+        position = end + close.len();
+        Some(start..end)
+    })
+}
+
+/// Undoes XML's escaping, noting where each byte of the text came from so that
+/// diagnostics can point back into the XML.
+fn unescape(raw: &str) -> (String, Vec<usize>) {
+    let mut text = String::with_capacity(raw.len());
+    let mut offsets = Vec::with_capacity(raw.len() + 1);
+    let mut position = 0;
+
+    while let Some(c) = raw[position..].chars().next() {
+        let (decoded, length) = match c {
+            '&' => match decode_entity(&raw[position..]) {
+                Some(entity) => entity,
+                None => (c, 1),
+            },
+            _ => (c, c.len_utf8()),
+        };
+
+        text.push(decoded);
+        offsets.extend((0..decoded.len_utf8()).map(|byte| position + byte.min(length - 1)));
+        position += length;
+    }
+    offsets.push(raw.len());
+
+    (text, offsets)
+}
+
+/// The character an entity like `&lt;` or `&#xA;` at the start of `raw` stands
+/// for, and how many bytes it took up.
+fn decode_entity(raw: &str) -> Option<(char, usize)> {
+    let end = raw.find(';')?;
+    let decoded = match &raw[1..end] {
+        "lt" => '<',
+        "gt" => '>',
+        "amp" => '&',
+        "quot" => '"',
+        "apos" => '\'',
+        entity => {
+            let number = entity.strip_prefix('#')?;
+            let code_point = match number.strip_prefix('x').or_else(|| number.strip_prefix('X')) {
+                Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                None => number.parse().ok()?,
+            };
+            char::from_u32(code_point)?
+        }
+    };
+
+    Some((decoded, end + 1))
+}
+
+/// Escapes code to go back into the XML. Attributes need their quotes and line
+/// breaks escaped too, or a reader would end the value early or turn them into spaces.
+fn escape(text: &str, in_attribute: bool) -> String {
+    let mut escaped = String::with_capacity(text.len());
+
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' if in_attribute => escaped.push_str("&quot;"),
+            '\n' if in_attribute => escaped.push_str("&#xA;"),
+            '\r' if in_attribute => escaped.push_str("&#xD;"),
+            '\t' if in_attribute => escaped.push_str("&#x9;"),
+            _ => escaped.push(c),
+        }
+    }
+
+    escaped
+}
@@ -0,0 +1,61 @@
+use super::config::Config;
+use super::diff::unified_diff;
+use super::error::Error;
+use super::lang_config::LangConfig;
+use super::reporter::{Reporter, SkipReason};
+use std::fs;
+
+/// Formats `source` twice. Formatting code which is already formatted should
+/// change nothing, so if the second pass changes the first's output, we give
+/// back a diff between the two.
+pub fn idempotency_diff(source: &str, lang_config: &LangConfig) -> Result<Option<String>, Error> {
+    let first_pass = super::run(source, lang_config, None)?;
+    let second_pass = super::run(&first_pass, lang_config, None)?;
+
+    Ok(unified_diff(&first_pass, &second_pass, "first pass", "second pass"))
+}
+
+/// Like `idempotency_diff`, for the code inside a GameMaker Studio 1.4 `.gmx` file.
+fn gmx_idempotency_diff(xml: &str, lang_config: &LangConfig) -> Result<Option<String>, Error> {
+    let first_pass = super::format_gmx(xml, lang_config, false)?;
+    let second_pass = super::format_gmx(&first_pass, lang_config, false)?;
+
+    Ok(unified_diff(&first_pass, &second_pass, "first pass", "second pass"))
+}
+
+/// Checks every file in `config` with `idempotency_diff` without writing anything,
+/// telling `reporter` about each file which formats differently the second time.
+pub fn verify_idempotent(config: &Config, lang_config: &LangConfig, reporter: &mut dyn Reporter) -> Result<(), Error> {
+    let mut unstable = Vec::new();
+
+    for this_file in &config.files {
+        let contents = fs::read_to_string(this_file).map_err(|e| Error::io(this_file, e))?;
+        if super::ignores_file(this_file, &contents) {
+            reporter.on_skipped(this_file, SkipReason::Ignored);
+            continue;
+        }
+
+        let diff = if super::is_gmx_file(this_file) {
+            gmx_idempotency_diff(&contents, lang_config)
+        } else {
+            idempotency_diff(&contents, lang_config)
+        };
+        let error = match diff {
+            Ok(None) => continue,
+            Ok(Some(diff)) => Error::Unstable { path: None, diff },
+            Err(e) => e,
+        };
+
+        let error = error.in_file(this_file);
+        reporter.on_error(this_file, Some(&contents), &error);
+        if let Error::Unstable { .. } = error {
+            unstable.push(error);
+        }
+    }
+
+    if unstable.is_empty() == false {
+        return Err(Error::Files(unstable));
+    }
+
+    Ok(())
+}
@@ -1,7 +1,8 @@
+use super::config::ConfigError;
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
 use std::{ffi::OsStr, fs};
-use toml;
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct LangConfig {
@@ -36,31 +37,67 @@ impl Default for LangConfig {
 }
 
 impl LangConfig {
-    pub fn new(input_path: &PathBuf) -> LangConfig {
-        let names = vec![
-            OsStr::new("gml_fmt.toml"),
-            OsStr::new(".gml_fmt.toml"),
-            OsStr::new(".gml_fmt"),
-        ];
-
-        for entry in fs::read_dir(input_path).expect(&format!("Error reading directory {:?}", input_path)) {
-            let entry = entry.expect(&format!("Error reading file"));
-            let path = entry.path();
-
-            if path.is_file() {
-                let fname = path.file_name().expect("Error reading filename.");
-
-                if names.contains(&fname) {
-                    let lang_config: LangConfig = toml::from_str(&fs::read_to_string(path).unwrap()).unwrap();
-                    return lang_config;
-                }
-            }
+    /// Reads the `gml_fmt.toml` in `input_path`, or gives back the defaults if there isn't one.
+    pub fn load(input_path: &PathBuf) -> Result<LangConfig, ConfigError> {
+        read_config_file(input_path)
+    }
+}
+
+/// Reads the `gml_fmt.toml` in `input_path` as a `T`, which is `T::default()` if there isn't one.
+pub(crate) fn read_config_file<T: DeserializeOwned + Default>(input_path: &PathBuf) -> Result<T, ConfigError> {
+    let path = match find_config_file(input_path)? {
+        Some(path) => path,
+        None => return Ok(T::default()),
+    };
+
+    let contents = fs::read_to_string(&path).map_err(|e| ConfigError::Unreadable {
+        path: path.clone(),
+        message: e.to_string(),
+    })?;
+
+    toml::from_str(&contents).map_err(|e| {
+        let (line, column) = match e.line_col() {
+            Some((line, column)) => (Some(line as u32 + 1), Some(column as u32 + 1)),
+            None => (None, None),
+        };
+
+        // toml puts the location at the end of its message, but we keep it separately.
+        let message = e.to_string();
+        let message = match message.rsplit_once(" at line ") {
+            Some((message, _)) if line.is_some() => message.to_owned(),
+            _ => message,
+        };
+
+        ConfigError::BadConfigFile {
+            path,
+            line,
+            column,
+            message,
         }
+    })
+}
 
-        LangConfig {
-            newlines_at_end: 1,
-            use_spaces: true,
-            space_size: 4,
+/// Finds the `gml_fmt.toml` (or one of its aliases) in the given directory.
+pub(crate) fn find_config_file(input_path: &PathBuf) -> Result<Option<PathBuf>, ConfigError> {
+    let names = [
+        OsStr::new("gml_fmt.toml"),
+        OsStr::new(".gml_fmt.toml"),
+        OsStr::new(".gml_fmt"),
+    ];
+
+    let entries = fs::read_dir(input_path).map_err(|e| ConfigError::Unreadable {
+        path: input_path.clone(),
+        message: e.to_string(),
+    })?;
+
+    // An entry we can't read can't be the config file we're looking for.
+    for entry in entries.filter_map(Result::ok) {
+        let path = entry.path();
+
+        if path.is_file() && path.file_name().is_some_and(|name| names.contains(&name)) {
+            return Ok(Some(path));
         }
     }
+
+    Ok(None)
 }
@@ -1,4 +1,10 @@
-#[derive(Debug, PartialEq, Copy, Clone)]
+use serde::Serialize;
+use std::fmt;
+use std::ops::Range;
+
+/// What kind of token a `Token` is. Tokens which carry text borrow it from the source.
+#[derive(Debug, PartialEq, Copy, Clone, Serialize)]
+#[non_exhaustive]
 pub enum TokenType<'a> {
     LeftParen,
     RightParen,
@@ -100,24 +106,122 @@ pub enum TokenType<'a> {
     UnidentifiedInput(&'a str),
 }
 
-#[derive(Debug, PartialEq, Copy, Clone)]
+impl<'a> TokenType<'a> {
+    /// The variant's name, such as `Identifier`, without any text it carries.
+    pub fn name(&self) -> &'static str {
+        match self {
+            TokenType::LeftParen => "LeftParen",
+            TokenType::RightParen => "RightParen",
+            TokenType::LeftBrace => "LeftBrace",
+            TokenType::RightBrace => "RightBrace",
+            TokenType::LeftBracket => "LeftBracket",
+            TokenType::RightBracket => "RightBracket",
+            TokenType::Comma => "Comma",
+            TokenType::Dot => "Dot",
+            TokenType::Colon => "Colon",
+            TokenType::Semicolon => "Semicolon",
+            TokenType::Slash => "Slash",
+            TokenType::Backslash => "Backslash",
+            TokenType::Star => "Star",
+            TokenType::Mod => "Mod",
+            TokenType::Hashtag => "Hashtag",
+            TokenType::PlusEquals => "PlusEquals",
+            TokenType::MinusEquals => "MinusEquals",
+            TokenType::StarEquals => "StarEquals",
+            TokenType::SlashEquals => "SlashEquals",
+            TokenType::BitXorEquals => "BitXorEquals",
+            TokenType::BitOrEquals => "BitOrEquals",
+            TokenType::BitAndEquals => "BitAndEquals",
+            TokenType::ModEquals => "ModEquals",
+            TokenType::ListIndexer => "ListIndexer",
+            TokenType::MapIndexer => "MapIndexer",
+            TokenType::GridIndexer => "GridIndexer",
+            TokenType::ArrayIndexer => "ArrayIndexer",
+            TokenType::Minus => "Minus",
+            TokenType::Plus => "Plus",
+            TokenType::Incrementer => "Incrementer",
+            TokenType::Decrementer => "Decrementer",
+            TokenType::Bang => "Bang",
+            TokenType::Hook => "Hook",
+            TokenType::Tilde => "Tilde",
+            TokenType::LessThanGreaterThan => "LessThanGreaterThan",
+            TokenType::LogicalAnd => "LogicalAnd",
+            TokenType::LogicalOr => "LogicalOr",
+            TokenType::LogicalXor => "LogicalXor",
+            TokenType::BitAnd => "BitAnd",
+            TokenType::BitOr => "BitOr",
+            TokenType::BitXor => "BitXor",
+            TokenType::BitLeft => "BitLeft",
+            TokenType::BitRight => "BitRight",
+            TokenType::BangEqual => "BangEqual",
+            TokenType::Equal => "Equal",
+            TokenType::EqualEqual => "EqualEqual",
+            TokenType::Greater => "Greater",
+            TokenType::GreaterEqual => "GreaterEqual",
+            TokenType::Less => "Less",
+            TokenType::LessEqual => "LessEqual",
+            TokenType::Macro(_) => "Macro",
+            TokenType::RegionBegin(_) => "RegionBegin",
+            TokenType::RegionEnd(_) => "RegionEnd",
+            TokenType::Define => "Define",
+            TokenType::Var => "Var",
+            TokenType::GlobalVar => "GlobalVar",
+            TokenType::If => "If",
+            TokenType::Else => "Else",
+            TokenType::Return => "Return",
+            TokenType::For => "For",
+            TokenType::Repeat => "Repeat",
+            TokenType::With => "With",
+            TokenType::While => "While",
+            TokenType::Do => "Do",
+            TokenType::Until => "Until",
+            TokenType::Switch => "Switch",
+            TokenType::Case => "Case",
+            TokenType::DefaultCase => "DefaultCase",
+            TokenType::Break => "Break",
+            TokenType::Exit => "Exit",
+            TokenType::Enum => "Enum",
+            TokenType::AndAlias => "AndAlias",
+            TokenType::OrAlias => "OrAlias",
+            TokenType::XorAlias => "XorAlias",
+            TokenType::NotAlias => "NotAlias",
+            TokenType::ModAlias => "ModAlias",
+            TokenType::Div => "Div",
+            TokenType::Then => "Then",
+            TokenType::Newline(_) => "Newline",
+            TokenType::Identifier(_) => "Identifier",
+            TokenType::String(_) => "String",
+            TokenType::Number(_) => "Number",
+            TokenType::NumberStartDot(_) => "NumberStartDot",
+            TokenType::NumberEndDot(_) => "NumberEndDot",
+            TokenType::Comment(_) => "Comment",
+            TokenType::MultilineComment(_) => "MultilineComment",
+            TokenType::UnidentifiedInput(_) => "UnidentifiedInput",
+        }
+    }
+}
+
+/// A token as it was scanned from the source. Lines and columns are 0-based.
+#[derive(Debug, PartialEq, Clone, Serialize)]
 pub struct Token<'a> {
     pub token_type: TokenType<'a>,
     pub line_number: u32,
     pub column_number: u32,
+    /// The bytes of the source this token was scanned from.
+    pub span: Range<u32>,
 }
 
 impl<'a> Token<'a> {
-    pub fn new(token_type: TokenType, line_number: u32, column_number: u32) -> Token {
+    pub fn new(token_type: TokenType, line_number: u32, column_number: u32, span: Range<u32>) -> Token {
         Token {
             token_type,
             line_number,
             column_number,
+            span,
         }
     }
 }
 
-use std::fmt;
 impl<'a> fmt::Display for Token<'a> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(
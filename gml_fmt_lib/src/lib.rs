@@ -1,83 +1,415 @@
+#![allow(clippy::bool_comparison, clippy::enum_variant_names)]
+
+pub mod ast;
+mod cache;
 mod config;
+pub mod cst;
+mod diagnostic;
+mod diff;
+mod error;
 mod expressions;
+mod git;
+mod gmx;
+mod idempotency;
 mod lang_config;
 mod lex_token;
+mod line_index;
+mod line_ranges;
 mod parser;
 mod printer;
+mod project;
+mod report;
+mod reporter;
 mod scanner;
 mod statements;
+mod verify;
+pub mod visit;
+mod writer;
 
-use anyhow::Result as AnyResult;
+use cache::{hash_contents, Cache};
 use parser::Parser;
 use printer::Printer;
+use reporter::{Event, FileEvents};
+use statements::StmtBox;
+use std::collections::BTreeMap;
 use std::fs;
+use std::ops::RangeInclusive;
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::thread;
+use std::time::Instant;
 
-pub use config::{Config, PrintFlags};
+pub use ast::Ast;
+pub use cache::CACHE_FILE_NAME;
+pub use config::{Config, ConfigError, FileFilters, PrintFlags, TraversalError};
+pub use cst::Cst;
+pub use diagnostic::{render_error, Diagnostic, Diagnostics, Severity};
+pub use error::Error;
+pub use git::{format_staged, GitChanges};
+pub use gmx::{format_gmx, is_gmx_file};
+pub use idempotency::{idempotency_diff, verify_idempotent};
 pub use lang_config::LangConfig;
+pub use line_index::LineIndex;
+pub use project::ResourceKind;
+pub use report::{FileError, FileReport, FileStatus, Report, Totals};
+pub use reporter::{Reporter, Silent, SkipReason};
+pub use verify::verify_tokens;
+pub use writer::{write_formatted, Backup};
 
-pub fn run_with_config(config: &Config, lang_config: &LangConfig) -> AnyResult<()> {
-    let log = config.print_flags.contains(PrintFlags::LOGS);
-    let overwrite = config.print_flags.contains(PrintFlags::OVERWRITE);
+/// Formats every file in `config`, telling `reporter` what happens to each, and
+/// fails if any file couldn't be formatted or written.
+pub fn run_with_config(config: &Config, lang_config: &LangConfig, reporter: &mut dyn Reporter) -> Result<(), Error> {
+    let (report, errors) = format_files_with_errors(config, lang_config, reporter);
 
-    for this_file in &config.files {
-        let contents = fs::read_to_string(this_file)?;
+    let failures: Vec<_> = report
+        .files
+        .into_iter()
+        .zip(errors)
+        .filter_map(|(file, error)| match file.status {
+            FileStatus::Failed => error,
+            FileStatus::VerificationFailed => Some(Error::Verification {
+                path: Some(file.path),
+                diagnostic: file.diagnostics.into_iter().next()?,
+            }),
+            _ => None,
+        })
+        .collect();
 
-        if contents.contains("// @gml_fmt ignore") {
-            continue;
-        }
+    if failures.is_empty() {
+        Ok(())
+    } else {
+        Err(Error::Files(failures))
+    }
+}
 
-        if log {
-            println!("=========INPUT=========");
-            println!("{}", contents);
-        }
+/// Formats every file in `config`, like `run_with_config`, and reports what
+/// happened to each of them rather than stopping at the first failure.
+pub fn format_files(config: &Config, lang_config: &LangConfig, reporter: &mut dyn Reporter) -> Report {
+    format_files_with_errors(config, lang_config, reporter).0
+}
 
-        let mut ast_log = if config.print_flags.contains(PrintFlags::LOG_AST) {
-            Some(String::new())
-        } else {
-            None
-        };
+/// Like `format_files`, but also gives back the error behind each failed file,
+/// in the same order as the report's files.
+fn format_files_with_errors(
+    config: &Config,
+    lang_config: &LangConfig,
+    reporter: &mut dyn Reporter,
+) -> (Report, Vec<Option<Error>>) {
+    let started = Instant::now();
+    let jobs = config.jobs.max(1).min(config.files.len().max(1));
+    let next_file = AtomicUsize::new(0);
+    let mut reports = Vec::with_capacity(config.files.len());
+    let mut errors = Vec::with_capacity(config.files.len());
+
+    let mut cache = load_cache(config, lang_config);
+
+    thread::scope(|scope| {
+        let (tx, rx) = mpsc::channel();
 
-        match run(&contents, lang_config, ast_log.as_mut()) {
-            Ok(output) => {
-                if log {
-                    println!("=========OUTPUT=========");
-                    println!("{}", output);
+        for _ in 0..jobs {
+            let tx = tx.clone();
+            let next_file = &next_file;
+            let cache = cache.as_ref();
+            scope.spawn(move || loop {
+                let index = next_file.fetch_add(1, Ordering::Relaxed);
+                if index >= config.files.len() {
+                    break;
                 }
 
-                if let Some(ast) = ast_log {
-                    println!("==========AST===========");
-                    println!("{}", ast);
+                let mut events = FileEvents::default();
+                let (report, error) = format_file(&config.files[index], config, lang_config, cache, &mut events);
+                if tx.send((index, events, report, error)).is_err() {
+                    break;
                 }
+            });
+        }
+        drop(tx);
+
+        // Workers finish out of order, so we hold results back until
+        // every file before them has been reported.
+        let mut pending = BTreeMap::new();
+        let mut next_to_report = 0;
+        for (index, events, report, error) in rx {
+            pending.insert(index, (events, report, error));
 
-                if overwrite {
-                    fs::write(this_file, output)?;
+            while let Some((events, report, error)) = pending.remove(&next_to_report) {
+                events.replay(&report.path, reporter);
+                if let Some(e) = &error {
+                    reporter.on_error(&report.path, events.source.as_deref(), e);
                 }
-            }
-            Err(e) => {
-                println!("Could not parse file {:?}", this_file);
-                println!("{}", e);
+                reports.push(report);
+                errors.push(error);
+                next_to_report += 1;
             }
         }
+    });
+
+    save_cache(cache.as_mut(), config, &reports);
+
+    (Report::new(reports, started.elapsed()), errors)
+}
+
+/// Formats one of `config`'s files just as `format_files` would, and reports what
+/// happened to it. This is for callers, like watch mode, who hear about files one
+/// at a time.
+pub fn format_single_file(
+    this_file: &Path,
+    config: &Config,
+    lang_config: &LangConfig,
+    reporter: &mut dyn Reporter,
+) -> FileReport {
+    let mut cache = load_cache(config, lang_config);
+
+    let mut events = FileEvents::default();
+    let (report, error) = format_file(this_file, config, lang_config, cache.as_ref(), &mut events);
+    events.replay(this_file, reporter);
+    if let Some(e) = &error {
+        reporter.on_error(this_file, events.source.as_deref(), e);
     }
 
-    Ok(())
+    save_cache(cache.as_mut(), config, std::slice::from_ref(&report));
+    report
 }
 
-pub fn run(source: &str, lang_config: &LangConfig, print_ast: Option<&mut String>) -> AnyResult<String> {
-    let source_size = source.len();
-    match Parser::new(source).build_ast() {
-        Ok(ast) => {
-            if let Some(give_ast) = print_ast {
-                *give_ast = format!("{:#?}", ast);
+fn load_cache(config: &Config, lang_config: &LangConfig) -> Option<Cache> {
+    // Logs and line ranges both need every file run through the formatter.
+    let use_cache =
+        config.lines.is_empty() && config.print_flags.intersects(PrintFlags::LOGS | PrintFlags::LOG_AST) == false;
+    match &config.cache {
+        Some(cache_path) if use_cache => Some(Cache::load(cache_path, lang_config)),
+        _ => None,
+    }
+}
+
+fn save_cache(cache: Option<&mut Cache>, config: &Config, reports: &[FileReport]) {
+    if let (Some(cache), Some(cache_path)) = (cache, &config.cache) {
+        for report in reports {
+            if let Some(hash) = &report.formatted_hash {
+                cache.record(&report.path, hash.clone());
             }
+        }
+
+        // A cache we can't save only costs us time on the next run.
+        let _ = cache.save(cache_path);
+    }
+}
 
-            let printer = Printer::new(source_size / 2, lang_config).autoformat(&ast);
+/// Formats one file, holding anything the reporter should hear in `events` so
+/// that concurrent runs can report it in order.
+fn format_file(
+    this_file: &Path,
+    config: &Config,
+    lang_config: &LangConfig,
+    cache: Option<&Cache>,
+    events: &mut FileEvents,
+) -> (FileReport, Option<Error>) {
+    let started = Instant::now();
+    let mut report = FileReport::new(this_file.to_path_buf());
+    report.resource = config.resource_name(this_file).map(String::from);
 
-            Ok(printer.get_output(source_size))
-        }
+    let result = try_format_file(
+        this_file,
+        config,
+        lang_config,
+        cache,
+        &mut events.source,
+        &mut events.events,
+        &mut report,
+    );
+    if let Err(e) = &result {
+        report.status = FileStatus::Failed;
+        report.error = Some(FileError::new(e));
+    }
+
+    report.time_ms = report::millis(started.elapsed());
+    (report, result.err())
+}
+
+fn try_format_file(
+    this_file: &Path,
+    config: &Config,
+    lang_config: &LangConfig,
+    cache: Option<&Cache>,
+    source: &mut Option<String>,
+    events: &mut Vec<Event>,
+    report: &mut FileReport,
+) -> Result<(), Error> {
+    let overwrite = config.print_flags.contains(PrintFlags::OVERWRITE);
+
+    let contents: &str = source.insert(fs::read_to_string(this_file).map_err(|e| Error::io(this_file, e))?);
+    report.bytes_before = contents.len();
+    report.bytes_after = contents.len();
+
+    if cache.is_some_and(|cache| cache.is_formatted(this_file, contents)) {
+        report.formatted_hash = Some(hash_contents(contents));
+        events.push(Event::Skipped(SkipReason::Cached));
+        return Ok(());
+    }
+
+    if ignores_file(this_file, contents) {
+        report.status = FileStatus::Ignored;
+        events.push(Event::Skipped(SkipReason::Ignored));
+        return Ok(());
+    }
+
+    events.push(Event::Start);
+
+    let mut ast = if config.print_flags.contains(PrintFlags::LOG_AST) {
+        Some(String::new())
+    } else {
+        None
+    };
 
+    // Line ranges don't mean anything inside XML, so `.gmx` files are formatted whole.
+    let is_gmx = is_gmx_file(this_file);
+    let result = if is_gmx {
+        format_gmx(contents, lang_config, config.verify).map(|output| Formatted {
+            output,
+            diagnostics: Vec::new(),
+        })
+    } else if config.lines.is_empty() {
+        Ok(run_recovering(contents, lang_config, ast.as_mut()))
+    } else {
+        run_lines(contents, lang_config, &config.lines).map(|output| Formatted {
+            output,
+            diagnostics: Vec::new(),
+        })
+    };
+
+    let formatted = match result {
+        Ok(formatted) => formatted,
+        // `format_gmx` verifies each piece of code itself.
+        Err(Error::Verification { diagnostic, .. }) => {
+            report.status = FileStatus::VerificationFailed;
+            report.error = Some(FileError::from_diagnostic(&diagnostic));
+            report.diagnostics = vec![diagnostic.clone()];
+            events.push(Event::Error(Error::Verification {
+                path: Some(this_file.to_path_buf()),
+                diagnostic,
+            }));
+            return Ok(());
+        }
         Err(e) => {
-            anyhow::bail!("{}", e);
+            report.status = FileStatus::ParseError;
+            report.error = Some(FileError::new(&e));
+            if let Error::Parse { diagnostics, .. } = &e {
+                report.diagnostics = diagnostics.to_vec();
+            }
+            events.push(Event::Error(e.in_file(this_file)));
+            return Ok(());
+        }
+    };
+    let output = &formatted.output;
+
+    if config.verify && is_gmx == false && output != contents {
+        if let Err(diagnostic) = verify_tokens(contents, output) {
+            report.status = FileStatus::VerificationFailed;
+            report.error = Some(FileError::from_diagnostic(&diagnostic));
+            report.diagnostics = vec![diagnostic.clone()];
+            events.push(Event::Formatted { formatted, ast });
+            events.push(Event::Error(Error::Verification {
+                path: Some(this_file.to_path_buf()),
+                diagnostic,
+            }));
+            return Ok(());
         }
     }
+
+    report.bytes_after = output.len();
+    if output != contents {
+        report.status = FileStatus::Reformatted;
+    }
+
+    if overwrite {
+        write_formatted(this_file, contents, output, &config.backup).map_err(|e| Error::io(this_file, e))?;
+    }
+
+    if formatted.diagnostics.is_empty() {
+        // Files with problems stay out of the cache, so we keep reporting them.
+        report.formatted_hash = match (overwrite, output == contents) {
+            (_, true) => Some(hash_contents(contents)),
+            (true, false) => Some(hash_contents(output)),
+            (false, false) => None,
+        };
+    } else {
+        report.status = if output == contents {
+            FileStatus::ParseError
+        } else {
+            FileStatus::FormattedWithErrors
+        };
+        report.error = Some(FileError::from_diagnostic(&formatted.diagnostics[0]));
+        report.diagnostics = formatted.diagnostics.clone();
+    }
+
+    events.push(Event::Formatted { formatted, ast });
+    Ok(())
+}
+
+/// Whether a file asks, with `// @gml_fmt ignore`, to be left alone. Each piece of
+/// code in a `.gmx` file asks for itself, so those are never ignored whole.
+pub fn ignores_file(path: &Path, contents: &str) -> bool {
+    is_gmx_file(path) == false && contents.contains("// @gml_fmt ignore")
+}
+
+/// Parses `source` into a syntax tree, or gives back every problem we found in it.
+pub fn parse(source: &str) -> Result<Ast<'_>, Diagnostics> {
+    let (statements, diagnostics) = Parser::new(source).build_ast_recovering();
+
+    if diagnostics.is_empty() {
+        Ok(Ast::new(statements, source))
+    } else {
+        Err(Diagnostics(diagnostics))
+    }
+}
+
+/// Prints a syntax tree from `parse` as formatted code.
+pub fn format_ast(ast: &Ast, lang_config: &LangConfig) -> String {
+    print_ast_to_string(ast.source(), &ast.statements, lang_config, None)
+}
+
+/// Formats `source`, failing with every problem we find if it can't be parsed.
+pub fn run(source: &str, lang_config: &LangConfig, print_ast: Option<&mut String>) -> Result<String, Error> {
+    let ast = Parser::new(source).build_ast()?;
+
+    Ok(print_ast_to_string(source, &ast, lang_config, print_ast))
+}
+
+/// What formatting a file gave us, and every problem we found along the way.
+#[derive(Debug)]
+pub struct Formatted {
+    pub output: String,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+/// Like `run`, but never gives up on a file. Anything we can't parse is copied into
+/// the output exactly as it was written, and reported in `diagnostics`.
+pub fn run_recovering(source: &str, lang_config: &LangConfig, print_ast: Option<&mut String>) -> Formatted {
+    let (ast, diagnostics) = Parser::new(source).build_ast_recovering();
+
+    Formatted {
+        output: print_ast_to_string(source, &ast, lang_config, print_ast),
+        diagnostics,
+    }
+}
+
+fn print_ast_to_string(
+    source: &str,
+    ast: &[StmtBox],
+    lang_config: &LangConfig,
+    print_ast: Option<&mut String>,
+) -> String {
+    let source_size = source.len();
+    if let Some(give_ast) = print_ast {
+        *give_ast = format!("{:#?}", ast);
+    }
+
+    let printer = Printer::new(source_size / 2, lang_config).autoformat(ast);
+    printer.get_output(source_size)
+}
+
+/// Like `run`, but only formats the top-level statements overlapping `lines`.
+/// Everything else is copied from `source` untouched. Lines are 0-based and inclusive.
+pub fn run_lines(source: &str, lang_config: &LangConfig, lines: &[RangeInclusive<u32>]) -> Result<String, Error> {
+    line_ranges::format_lines(source, lang_config, lines)
 }
@@ -0,0 +1,59 @@
+use std::ops::Range;
+
+/// Looks up where byte offsets, like the spans on tokens and AST nodes, fall in a source.
+/// Lines and columns here are 0-based, and columns count characters rather than bytes.
+#[derive(Debug, Clone)]
+pub struct LineIndex<'a> {
+    source: &'a str,
+    line_starts: Vec<u32>,
+}
+
+impl<'a> LineIndex<'a> {
+    pub fn new(source: &'a str) -> LineIndex<'a> {
+        let mut line_starts = vec![0];
+        line_starts.extend(source.match_indices('\n').map(|(i, _)| i as u32 + 1));
+
+        LineIndex { source, line_starts }
+    }
+
+    pub fn source(&self) -> &'a str {
+        self.source
+    }
+
+    pub fn line_count(&self) -> u32 {
+        self.line_starts.len() as u32
+    }
+
+    /// The line `offset` is on.
+    pub fn line(&self, offset: u32) -> u32 {
+        self.line_starts.partition_point(|&start| start <= offset) as u32 - 1
+    }
+
+    /// The line and column of `offset`.
+    pub fn line_col(&self, offset: u32) -> (u32, u32) {
+        let line = self.line(offset);
+        let line_start = self.line_starts[line as usize] as usize;
+        let offset = (offset as usize).min(self.source.len());
+
+        (line, self.source[line_start..offset].chars().count() as u32)
+    }
+
+    /// The offset `line` starts at, or the end of the source for lines past the last one.
+    pub fn line_start(&self, line: u32) -> u32 {
+        self.line_starts
+            .get(line as usize)
+            .copied()
+            .unwrap_or(self.source.len() as u32)
+    }
+
+    /// The lines `span` covers, first and last. An empty span covers the line it's on.
+    pub fn lines(&self, span: &Range<u32>) -> (u32, u32) {
+        let start = self.line(span.start);
+        (start, self.line(span.end.saturating_sub(1)).max(start))
+    }
+
+    /// The text of the source `span` covers.
+    pub fn text(&self, span: &Range<u32>) -> &'a str {
+        &self.source[span.start as usize..span.end as usize]
+    }
+}
@@ -0,0 +1,90 @@
+use super::error::Error;
+use super::lang_config::LangConfig;
+use super::line_index::LineIndex;
+use super::parser::Parser;
+use super::statements::StmtBox;
+use std::ops::RangeInclusive;
+
+/// Formats only the top-level statements which overlap `lines`, and splices them back
+/// into `source`, leaving every other line byte-identical.
+pub(crate) fn format_lines(
+    source: &str,
+    lang_config: &LangConfig,
+    lines: &[RangeInclusive<u32>],
+) -> Result<String, Error> {
+    // We parse the whole file first, so we never format a piece of a file we couldn't format whole.
+    let ast = Parser::new(source).build_ast()?;
+
+    let line_index = LineIndex::new(source);
+    let chunks = statement_chunks(&ast, &line_index);
+
+    let mut output = String::with_capacity(source.len());
+    let mut copied_up_to = 0;
+    let mut chunks = chunks.iter().peekable();
+
+    while let Some(chunk) = chunks.next() {
+        if overlaps(chunk, lines) == false {
+            continue;
+        }
+
+        // Neighbouring chunks are formatted together, so the blank lines
+        // between them are handled just as they would be in a whole file.
+        let mut last_line = *chunk.end();
+        while let Some(next) = chunks.peek() {
+            if overlaps(next, lines) == false {
+                break;
+            }
+            last_line = *next.end();
+            chunks.next();
+        }
+
+        let start = line_index.line_start(*chunk.start()) as usize;
+        let end = line_index.line_start(last_line + 1) as usize;
+        let region = &source[start..end];
+
+        output.push_str(&source[copied_up_to..start]);
+        match super::run(region, lang_config, None) {
+            Ok(formatted) => {
+                output.push_str(formatted.trim_end());
+                output.push_str(&region[region.trim_end().len()..]);
+            }
+            // A region which only parses in the context of the whole file is left alone.
+            Err(_) => output.push_str(region),
+        }
+        copied_up_to = end;
+    }
+
+    output.push_str(&source[copied_up_to..]);
+    Ok(output)
+}
+
+/// Splits the file into runs of whole lines, each holding one or more top-level statements.
+/// Statements which share a line with the statement before them are kept in its chunk.
+fn statement_chunks(ast: &[StmtBox], line_index: &LineIndex) -> Vec<RangeInclusive<u32>> {
+    let line_count = line_index.line_count();
+    let mut starts = vec![0];
+    let mut previous_end = None;
+
+    for stmt in ast {
+        let (start, end) = line_index.lines(&stmt.span);
+        let starts_own_line = previous_end.is_some_and(|previous_end| start > previous_end);
+        if starts_own_line && start > *starts.last().unwrap() {
+            starts.push(start);
+        }
+        previous_end = Some(previous_end.map_or(end, |previous_end: u32| previous_end.max(end)));
+    }
+
+    let mut chunks = Vec::with_capacity(starts.len());
+    for (i, start) in starts.iter().enumerate() {
+        let end = starts.get(i + 1).map_or(line_count.saturating_sub(1), |next| next - 1);
+        chunks.push(*start..=end.max(*start));
+    }
+
+    chunks
+}
+
+fn overlaps(chunk: &RangeInclusive<u32>, lines: &[RangeInclusive<u32>]) -> bool {
+    lines
+        .iter()
+        .any(|range| range.start() <= chunk.end() && range.end() >= chunk.start())
+}
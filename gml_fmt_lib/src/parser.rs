@@ -1,36 +1,68 @@
+use super::cst::{Cst, TokenStream};
+use super::diagnostic::{Diagnostic, Diagnostics};
 use super::expressions::*;
 use super::lex_token::TokenType;
 use super::lex_token::*;
-use super::scanner::Scanner;
+use super::line_index::LineIndex;
+use super::printer::Printer;
 use super::statements::*;
-use anyhow::Result as AnyResult;
 use std::iter::Peekable;
 
+type ParseResult<T> = Result<T, Diagnostic>;
+
 pub struct Parser<'a> {
     pub ast: Vec<StmtBox<'a>>,
+    input: &'a str,
+    /// Shared by every diagnostic, so finding their lines doesn't rescan the file.
+    line_index: LineIndex<'a>,
     allow_unidentified: bool,
-    scanner: Peekable<Scanner<'a>>,
+    scanner: Peekable<TokenStream<'a>>,
     can_pair: bool,
     leftover_stmts: Vec<StmtBox<'a>>,
     check_leftovers: bool,
+    last_end: u32,
+    diagnostics: Vec<Diagnostic>,
 }
 
 impl<'a> Parser<'a> {
     pub fn new(input: &'a str) -> Parser<'a> {
+        Parser::from_cst(input, Cst::new(input))
+    }
+
+    /// Parses the tokens of `cst`, which was built from `input`.
+    pub fn from_cst(input: &'a str, cst: Cst<'a>) -> Parser<'a> {
         Parser {
             ast: Vec::new(),
-            scanner: Scanner::new(input).into_iter().peekable(),
+            input,
+            line_index: LineIndex::new(input),
+            scanner: cst.into_token_stream().peekable(),
             allow_unidentified: false,
             can_pair: true,
             leftover_stmts: Vec::new(),
             check_leftovers: false,
+            last_end: 0,
+            diagnostics: Vec::new(),
+        }
+    }
+
+    /// Parses the whole file, failing with every problem we find.
+    pub fn build_ast(self) -> Result<Vec<StmtBox<'a>>, Diagnostics> {
+        let (ast, diagnostics) = self.build_ast_recovering();
+
+        if diagnostics.is_empty() {
+            Ok(ast)
+        } else {
+            Err(Diagnostics(diagnostics))
         }
     }
 
-    pub fn build_ast(mut self) -> AnyResult<Vec<StmtBox<'a>>> {
-        while let Some(_) = self.scanner.peek() {
+    /// Parses the whole file, even if parts of it can't be parsed. Each part we
+    /// can't parse becomes a `Statement::Unknown`, which is printed exactly as
+    /// it was written, and gets a diagnostic explaining why.
+    pub fn build_ast_recovering(mut self) -> (Vec<StmtBox<'a>>, Vec<Diagnostic>) {
+        while self.scanner.peek().is_some() {
             self.can_pair = true;
-            let ret = self.statement()?;
+            let ret = self.statement_or_recover();
             self.ast.push(ret);
 
             if self.check_leftovers {
@@ -39,10 +71,128 @@ impl<'a> Parser<'a> {
             }
         }
 
-        Ok(self.ast)
+        (self.ast, self.diagnostics)
+    }
+
+    /// Parses a statement. If we can't, we record why, rewind to where the
+    /// statement started, and skip ahead to somewhere we can start again.
+    fn statement_or_recover(&mut self) -> StmtBox<'a> {
+        let scanner = self.scanner.clone();
+        let leftover_count = self.leftover_stmts.len();
+        let check_leftovers = self.check_leftovers;
+        let last_end = self.last_end;
+
+        match self.statement() {
+            Ok(stmt) => stmt,
+            Err(diagnostic) => {
+                self.diagnostics.push(diagnostic);
+
+                self.scanner = scanner;
+                self.leftover_stmts.truncate(leftover_count);
+                self.check_leftovers = check_leftovers;
+                self.last_end = last_end;
+                self.allow_unidentified = false;
+                self.can_pair = true;
+
+                self.unknown_statement()
+            }
+        }
     }
 
-    fn statement(&mut self) -> AnyResult<StmtBox<'a>> {
+    /// Skips tokens until we reach a `;`, a `}` closing whatever we skipped into,
+    /// or the start of another statement. Expressions often run over several lines,
+    /// so outside of brackets a newline only ends the statement if the next line
+    /// starts with something a statement could start with.
+    fn unknown_statement(&mut self) -> StmtBox<'a> {
+        let first = self.consume_next();
+        let start = first.span.start;
+
+        let mut depth: usize = 0;
+        let mut brackets: usize = 0;
+        let mut finished = false;
+        match first.token_type {
+            TokenType::LeftBrace => depth += 1,
+            TokenType::Semicolon | TokenType::RightBrace => finished = true,
+            token_type if opens_bracket(token_type) => brackets += 1,
+            _ => {}
+        }
+
+        while finished == false {
+            let token_type = match self.scanner.peek() {
+                Some(token) => token.token_type,
+                None => break,
+            };
+
+            match token_type {
+                TokenType::LeftBrace => depth += 1,
+                TokenType::RightBrace if depth == 0 => break,
+                TokenType::RightBrace => {
+                    depth -= 1;
+                    finished = depth == 0;
+                }
+                TokenType::Semicolon => finished = depth == 0,
+                TokenType::RightParen | TokenType::RightBracket => brackets = brackets.saturating_sub(1),
+                _ if opens_bracket(token_type) => brackets += 1,
+                TokenType::Newline(_) if depth == 0 && brackets == 0 && self.next_line_starts_statement() => break,
+                _ if depth == 0 && starts_statement(token_type) => break,
+                _ => {}
+            }
+
+            self.consume_next();
+        }
+
+        let end = self
+            .scanner
+            .peek()
+            .map_or(self.input.len() as u32, |token| token.span.start);
+        let source = self.input[start as usize..end as usize].trim_end();
+
+        let mut stmt = StatementWrapper::new(Statement::Unknown { source }, false);
+        stmt.span = start..self.last_end;
+        stmt
+    }
+
+    /// Whether the first thing after the newline we're looking at, past any
+    /// blank lines and comments, could start a statement or close a block.
+    fn next_line_starts_statement(&self) -> bool {
+        let mut scanner = self.scanner.clone();
+        let next = scanner.find(|token| {
+            matches!(
+                token.token_type,
+                TokenType::Newline(_) | TokenType::Comment(_) | TokenType::MultilineComment(_)
+            ) == false
+        });
+
+        match next {
+            Some(token) => {
+                matches!(token.token_type, TokenType::Identifier(_) | TokenType::RightBrace)
+                    || starts_statement(token.token_type)
+            }
+            None => true,
+        }
+    }
+
+    fn statement(&mut self) -> ParseResult<StmtBox<'a>> {
+        let start = self.scanner.peek().map_or(self.last_end, |token| token.span.start);
+        let leftover_count = self.leftover_stmts.len();
+        let mut ret = self.statement_kind()?;
+
+        // A `var` which had statements split off its end stops where the first of them starts.
+        let end = match self.leftover_stmts.get(leftover_count) {
+            Some(leftover) if matches!(ret.statement, Statement::VariableDeclList { .. }) => {
+                start
+                    + self.input[start as usize..leftover.span.start as usize]
+                        .trim_end()
+                        .len() as u32
+            }
+            _ => self.last_end.max(start),
+        };
+        ret.span = start..end;
+
+        Ok(ret)
+    }
+
+    fn statement_kind(&mut self) -> ParseResult<StmtBox<'a>> {
         if let Some(token) = self.scanner.peek() {
             match token.token_type {
                 TokenType::Comment(_) => {
@@ -121,7 +271,7 @@ impl<'a> Parser<'a> {
         self.expression_statement()
     }
 
-    fn define_statement(&mut self) -> AnyResult<StmtBox<'a>> {
+    fn define_statement(&mut self) -> ParseResult<StmtBox<'a>> {
         let comments_after_control_word = self.get_newlines_and_comments();
         let script_name = self.expression()?;
         let mut body = vec![];
@@ -133,7 +283,7 @@ impl<'a> Parser<'a> {
                 }
 
                 _ => {
-                    body.push(self.statement()?);
+                    body.push(self.statement_or_recover());
                 }
             }
         }
@@ -148,8 +298,8 @@ impl<'a> Parser<'a> {
         ))
     }
 
-    fn series_var_declaration(&mut self) -> AnyResult<StmtBox<'a>> {
-        let starting_var_type = self.scanner.next().unwrap();
+    fn series_var_declaration(&mut self) -> ParseResult<StmtBox<'a>> {
+        let starting_var_type = self.consume_next();
         let comments_after_control_word = self.get_newlines_and_comments();
         let var_decl = self.var_declaration()?;
         let has_semicolon = self.check_next_consume(TokenType::Semicolon);
@@ -164,7 +314,7 @@ impl<'a> Parser<'a> {
         ))
     }
 
-    fn var_declaration(&mut self) -> AnyResult<DelimitedLines<'a, VariableDecl<'a>>> {
+    fn var_declaration(&mut self) -> ParseResult<DelimitedLines<'a, VariableDecl<'a>>> {
         let mut arguments: Vec<DelimitedLine<'a, VariableDecl<'a>>> = Vec::new();
 
         let end_delimiter;
@@ -180,7 +330,7 @@ impl<'a> Parser<'a> {
             let mut say_var_comments = None;
 
             if has_var {
-                say_var = Some(self.scanner.next().unwrap());
+                say_var = Some(self.consume_next());
                 say_var_comments = Some(self.get_newlines_and_comments());
             }
 
@@ -211,12 +361,13 @@ impl<'a> Parser<'a> {
 
                 _ => {
                     // Ah shit you suck.
+                    let start = var_expr.span.start;
                     let has_semicolon = self.check_next_consume(TokenType::Semicolon);
                     self.check_leftovers = true;
-                    self.leftover_stmts.push(StatementWrapper::new(
-                        Statement::ExpresssionStatement { expression: var_expr },
-                        has_semicolon,
-                    ));
+                    let mut leftover =
+                        StatementWrapper::new(Statement::ExpressionStatement { expression: var_expr }, has_semicolon);
+                    leftover.span = start..self.last_end;
+                    self.leftover_stmts.push(leftover);
                     end_delimiter = true; // we never woulda gotten here if not for you cursed end delimiters!
                     break;
                 }
@@ -247,16 +398,16 @@ impl<'a> Parser<'a> {
         })
     }
 
-    fn block(&mut self) -> AnyResult<StmtBox<'a>> {
+    fn block(&mut self) -> ParseResult<StmtBox<'a>> {
         let comments_after_lbrace = self.get_newlines_and_comments();
 
         let mut statements = Vec::new();
 
-        while let Some(_) = self.scanner.peek() {
+        while self.scanner.peek().is_some() {
             if self.check_next_consume(TokenType::RightBrace) {
                 break;
             } else {
-                statements.push(self.statement()?);
+                statements.push(self.statement_or_recover());
             }
         }
 
@@ -271,7 +422,7 @@ impl<'a> Parser<'a> {
         ))
     }
 
-    fn if_statement(&mut self) -> AnyResult<StmtBox<'a>> {
+    fn if_statement(&mut self) -> ParseResult<StmtBox<'a>> {
         let comments_after_control_word = self.get_newlines_and_comments();
         let condition = self.expression()?;
         let then_branch = self.statement()?;
@@ -295,7 +446,7 @@ impl<'a> Parser<'a> {
         ))
     }
 
-    fn while_with_repeat(&mut self, token: Token<'a>) -> AnyResult<StmtBox<'a>> {
+    fn while_with_repeat(&mut self, token: Token<'a>) -> ParseResult<StmtBox<'a>> {
         let comments_after_control_word = self.get_newlines_and_comments();
         let condition = self.expression()?;
         let body = self.statement()?;
@@ -312,7 +463,7 @@ impl<'a> Parser<'a> {
         ))
     }
 
-    fn do_until_statement(&mut self) -> AnyResult<StmtBox<'a>> {
+    fn do_until_statement(&mut self) -> ParseResult<StmtBox<'a>> {
         let comments_after_control_word = self.get_newlines_and_comments();
         let body = self.statement()?;
         let comments_between = self.get_newlines_and_comments();
@@ -331,7 +482,7 @@ impl<'a> Parser<'a> {
         ))
     }
 
-    fn switch_statement(&mut self) -> AnyResult<StmtBox<'a>> {
+    fn switch_statement(&mut self) -> ParseResult<StmtBox<'a>> {
         let comments_after_control_word = self.get_newlines_and_comments();
         let condition = self.expression()?;
         self.check_next_consume(TokenType::LeftBrace);
@@ -358,7 +509,7 @@ impl<'a> Parser<'a> {
                                 break;
                             }
                             _ => {
-                                statements.push(self.statement()?);
+                                statements.push(self.statement_or_recover());
                             }
                         }
                     }
@@ -384,7 +535,7 @@ impl<'a> Parser<'a> {
                                 break;
                             }
                             _ => {
-                                statements.push(self.statement()?);
+                                statements.push(self.statement_or_recover());
                             }
                         }
                     }
@@ -400,7 +551,10 @@ impl<'a> Parser<'a> {
                 TokenType::RightBrace => break,
 
                 _ => {
-                    anyhow::bail!("Unknown token {} in Switch statement", token);
+                    let message = format!("expected `case` or `default`, found `{}`", lexeme(token));
+                    let diagnostic = Diagnostic::error_at(&self.line_index, token, message)
+                        .with_help("everything inside a `switch` has to be under a `case` or `default` label");
+                    return Err(diagnostic);
                 }
             }
         }
@@ -420,7 +574,7 @@ impl<'a> Parser<'a> {
         ))
     }
 
-    fn for_statement(&mut self) -> AnyResult<StmtBox<'a>> {
+    fn for_statement(&mut self) -> ParseResult<StmtBox<'a>> {
         let comments_after_control_word = self.get_newlines_and_comments();
 
         self.check_next_consume(TokenType::LeftParen);
@@ -473,7 +627,7 @@ impl<'a> Parser<'a> {
         ))
     }
 
-    fn return_statement(&mut self) -> AnyResult<StmtBox<'a>> {
+    fn return_statement(&mut self) -> ParseResult<StmtBox<'a>> {
         let expression = if self.check_next(TokenType::Semicolon) {
             None
         } else {
@@ -484,17 +638,17 @@ impl<'a> Parser<'a> {
         Ok(StatementWrapper::new(Statement::Return { expression }, has_semicolon))
     }
 
-    fn break_statement(&mut self) -> AnyResult<StmtBox<'a>> {
+    fn break_statement(&mut self) -> ParseResult<StmtBox<'a>> {
         let has_semicolon = self.check_next_consume(TokenType::Semicolon);
         Ok(StatementWrapper::new(Statement::Break, has_semicolon))
     }
 
-    fn exit_statment(&mut self) -> AnyResult<StmtBox<'a>> {
+    fn exit_statment(&mut self) -> ParseResult<StmtBox<'a>> {
         let has_semicolon = self.check_next_consume(TokenType::Semicolon);
         Ok(StatementWrapper::new(Statement::Exit, has_semicolon))
     }
 
-    fn enum_declaration(&mut self) -> AnyResult<StmtBox<'a>> {
+    fn enum_declaration(&mut self) -> ParseResult<StmtBox<'a>> {
         let comments_after_control_word = self.get_newlines_and_comments();
         let name = self.expression()?;
 
@@ -515,17 +669,17 @@ impl<'a> Parser<'a> {
         ))
     }
 
-    fn expression_statement(&mut self) -> AnyResult<StmtBox<'a>> {
+    fn expression_statement(&mut self) -> ParseResult<StmtBox<'a>> {
         let expr = self.expression()?;
         let has_semicolon = self.check_next_consume(TokenType::Semicolon);
 
         Ok(StatementWrapper::new(
-            Statement::ExpresssionStatement { expression: expr },
+            Statement::ExpressionStatement { expression: expr },
             has_semicolon,
         ))
     }
 
-    fn expression(&mut self) -> AnyResult<ExprBox<'a>> {
+    fn expression(&mut self) -> ParseResult<ExprBox<'a>> {
         self.allow_unidentified = true;
         let ret = self.assignment()?;
         self.can_pair = true;
@@ -534,7 +688,7 @@ impl<'a> Parser<'a> {
         Ok(ret)
     }
 
-    fn assignment(&mut self) -> AnyResult<ExprBox<'a>> {
+    fn assignment(&mut self) -> ParseResult<ExprBox<'a>> {
         let mut expr = self.ternary()?;
 
         if self.can_pair {
@@ -549,16 +703,19 @@ impl<'a> Parser<'a> {
                     | TokenType::BitOrEquals
                     | TokenType::BitAndEquals
                     | TokenType::ModEquals => {
-                        let operator = self.scanner.next().unwrap();
+                        let operator = self.consume_next();
                         let comments_and_newlines_between_op_and_r = self.get_newlines_and_comments();
                         let assignment_expr = self.assignment()?;
 
-                        expr = self.create_expr_box_no_comment(Expr::Assign {
-                            left: expr,
-                            operator: operator,
-                            comments_and_newlines_between_op_and_r,
-                            right: assignment_expr,
-                        });
+                        expr = self.create_expr_box_no_comment(
+                            expr.span.start,
+                            Expr::Assign {
+                                left: expr,
+                                operator,
+                                comments_and_newlines_between_op_and_r,
+                                right: assignment_expr,
+                            },
+                        );
                     }
 
                     _ => {}
@@ -569,7 +726,7 @@ impl<'a> Parser<'a> {
         Ok(expr)
     }
 
-    fn ternary(&mut self) -> AnyResult<ExprBox<'a>> {
+    fn ternary(&mut self) -> ParseResult<ExprBox<'a>> {
         let mut expr = self.or()?;
 
         if self.check_next_consume(TokenType::Hook) {
@@ -579,92 +736,107 @@ impl<'a> Parser<'a> {
             let comments_and_newlines_after_colon = self.get_newlines_and_comments();
             let right = self.ternary()?;
 
-            expr = self.create_expr_box_no_comment(Expr::Ternary {
-                conditional: expr,
-                comments_and_newlines_after_q,
-                left,
-                comments_and_newlines_after_colon,
-                right,
-            });
+            expr = self.create_expr_box_no_comment(
+                expr.span.start,
+                Expr::Ternary {
+                    conditional: expr,
+                    comments_and_newlines_after_q,
+                    left,
+                    comments_and_newlines_after_colon,
+                    right,
+                },
+            );
         }
 
         Ok(expr)
     }
 
     // parse our Logical Operands here
-    fn or(&mut self) -> AnyResult<ExprBox<'a>> {
+    fn or(&mut self) -> ParseResult<ExprBox<'a>> {
         let mut left = self.and()?;
 
         if self.check_next_either(TokenType::LogicalOr, TokenType::OrAlias) {
-            let token = self.scanner.next().unwrap();
+            let token = self.consume_next();
             let comments_and_newlines_between_op_and_r = self.get_newlines_and_comments();
             let right = self.or()?;
 
-            left = self.create_expr_box_no_comment(Expr::Binary {
-                left,
-                operator: token,
-                comments_and_newlines_between_op_and_r,
-                right,
-            });
+            left = self.create_expr_box_no_comment(
+                left.span.start,
+                Expr::Binary {
+                    left,
+                    operator: token,
+                    comments_and_newlines_between_op_and_r,
+                    right,
+                },
+            );
         }
 
         Ok(left)
     }
 
-    fn and(&mut self) -> AnyResult<ExprBox<'a>> {
+    fn and(&mut self) -> ParseResult<ExprBox<'a>> {
         let mut left = self.xor()?;
 
         if self.check_next_either(TokenType::LogicalAnd, TokenType::AndAlias) {
-            let token = self.scanner.next().unwrap();
+            let token = self.consume_next();
             let comments_and_newlines_between_op_and_r = self.get_newlines_and_comments();
             let right = self.and()?;
 
-            left = self.create_expr_box_no_comment(Expr::Binary {
-                left,
-                operator: token,
-                comments_and_newlines_between_op_and_r,
-                right,
-            });
+            left = self.create_expr_box_no_comment(
+                left.span.start,
+                Expr::Binary {
+                    left,
+                    operator: token,
+                    comments_and_newlines_between_op_and_r,
+                    right,
+                },
+            );
         }
 
         Ok(left)
     }
 
-    fn xor(&mut self) -> AnyResult<ExprBox<'a>> {
+    fn xor(&mut self) -> ParseResult<ExprBox<'a>> {
         let mut left = self.equality()?;
 
         if self.check_next_either(TokenType::LogicalXor, TokenType::XorAlias) {
-            let token = self.scanner.next().unwrap();
+            let token = self.consume_next();
             let comments_and_newlines_between_op_and_r = self.get_newlines_and_comments();
             let right = self.xor()?;
 
-            left = self.create_expr_box_no_comment(Expr::Binary {
-                left,
-                operator: token,
-                comments_and_newlines_between_op_and_r,
-                right,
-            })
+            left = self.create_expr_box_no_comment(
+                left.span.start,
+                Expr::Binary {
+                    left,
+                    operator: token,
+                    comments_and_newlines_between_op_and_r,
+                    right,
+                },
+            )
         }
 
         Ok(left)
     }
 
-    fn equality(&mut self) -> AnyResult<ExprBox<'a>> {
+    fn equality(&mut self) -> ParseResult<ExprBox<'a>> {
         let mut expr = self.comparison()?;
 
         if self.can_pair {
             while let Some(t) = self.scanner.peek() {
                 if t.token_type == TokenType::EqualEqual || t.token_type == TokenType::BangEqual {
-                    let token = self.scanner.next().unwrap();
+                    let token = self.consume_next();
                     let comments_and_newlines_between_op_and_r = self.get_newlines_and_comments();
                     let right = self.comparison()?;
 
-                    expr = self.create_expr_box_no_comment(Expr::Binary {
-                        left: expr,
-                        operator: token,
-                        comments_and_newlines_between_op_and_r,
-                        right,
-                    });
+                    expr = self.create_expr_box_no_comment(
+                        expr.span.start,
+                        Expr::Binary {
+                            left: expr,
+                            operator: token,
+                            comments_and_newlines_between_op_and_r,
+                            right,
+                        },
+                    );
                 } else {
                     break;
                 }
@@ -674,23 +846,26 @@ impl<'a> Parser<'a> {
         Ok(expr)
     }
 
-    fn comparison(&mut self) -> AnyResult<ExprBox<'a>> {
+    fn comparison(&mut self) -> ParseResult<ExprBox<'a>> {
         let mut expr = self.binary()?;
 
         if self.can_pair {
             while let Some(t) = self.scanner.peek() {
                 match t.token_type {
                     TokenType::Greater | TokenType::GreaterEqual | TokenType::Less | TokenType::LessEqual => {
-                        let t = self.scanner.next().unwrap();
+                        let t = self.consume_next();
                         let comments_and_newlines_between_op_and_r = self.get_newlines_and_comments();
                         let right = self.binary()?;
 
-                        expr = self.create_expr_box_no_comment(Expr::Binary {
-                            left: expr,
-                            operator: t,
-                            comments_and_newlines_between_op_and_r,
-                            right,
-                        });
+                        expr = self.create_expr_box_no_comment(
+                            expr.span.start,
+                            Expr::Binary {
+                                left: expr,
+                                operator: t,
+                                comments_and_newlines_between_op_and_r,
+                                right,
+                            },
+                        );
                     }
                     _ => break,
                 };
@@ -700,23 +875,26 @@ impl<'a> Parser<'a> {
         Ok(expr)
     }
 
-    fn binary(&mut self) -> AnyResult<ExprBox<'a>> {
+    fn binary(&mut self) -> ParseResult<ExprBox<'a>> {
         let mut expr = self.bitshift()?;
 
         if self.can_pair {
             while let Some(t) = self.scanner.peek() {
                 match t.token_type {
                     TokenType::BitAnd | TokenType::BitOr | TokenType::BitXor => {
-                        let t = self.scanner.next().unwrap();
+                        let t = self.consume_next();
                         let comments_and_newlines_between_op_and_r = self.get_newlines_and_comments();
                         let right = self.bitshift()?;
 
-                        expr = self.create_expr_box_no_comment(Expr::Binary {
-                            left: expr,
-                            operator: t,
-                            comments_and_newlines_between_op_and_r,
-                            right,
-                        });
+                        expr = self.create_expr_box_no_comment(
+                            expr.span.start,
+                            Expr::Binary {
+                                left: expr,
+                                operator: t,
+                                comments_and_newlines_between_op_and_r,
+                                right,
+                            },
+                        );
                     }
                     _ => break,
                 }
@@ -726,23 +904,26 @@ impl<'a> Parser<'a> {
         Ok(expr)
     }
 
-    fn bitshift(&mut self) -> AnyResult<ExprBox<'a>> {
+    fn bitshift(&mut self) -> ParseResult<ExprBox<'a>> {
         let mut expr = self.addition()?;
 
         if self.can_pair {
             while let Some(t) = self.scanner.peek() {
                 match t.token_type {
                     TokenType::BitLeft | TokenType::BitRight => {
-                        let t = self.scanner.next().unwrap();
+                        let t = self.consume_next();
                         let comments_and_newlines_between_op_and_r = self.get_newlines_and_comments();
                         let right = self.addition()?;
 
-                        expr = self.create_expr_box_no_comment(Expr::Binary {
-                            left: expr,
-                            operator: t,
-                            comments_and_newlines_between_op_and_r,
-                            right,
-                        });
+                        expr = self.create_expr_box_no_comment(
+                            expr.span.start,
+                            Expr::Binary {
+                                left: expr,
+                                operator: t,
+                                comments_and_newlines_between_op_and_r,
+                                right,
+                            },
+                        );
                     }
                     _ => break,
                 }
@@ -752,23 +933,26 @@ impl<'a> Parser<'a> {
         Ok(expr)
     }
 
-    fn addition(&mut self) -> AnyResult<ExprBox<'a>> {
+    fn addition(&mut self) -> ParseResult<ExprBox<'a>> {
         let mut expr = self.multiplication()?;
 
         if self.can_pair {
             while let Some(t) = self.scanner.peek() {
                 match t.token_type {
                     TokenType::Minus | TokenType::Plus => {
-                        let token = self.scanner.next().unwrap();
+                        let token = self.consume_next();
                         let comments_and_newlines_between_op_and_r = self.get_newlines_and_comments();
                         let right = self.multiplication()?;
 
-                        expr = self.create_expr_box_no_comment(Expr::Binary {
-                            left: expr,
-                            operator: token,
-                            comments_and_newlines_between_op_and_r,
-                            right,
-                        });
+                        expr = self.create_expr_box_no_comment(
+                            expr.span.start,
+                            Expr::Binary {
+                                left: expr,
+                                operator: token,
+                                comments_and_newlines_between_op_and_r,
+                                right,
+                            },
+                        );
                     }
                     _ => break,
                 };
@@ -778,23 +962,26 @@ impl<'a> Parser<'a> {
         Ok(expr)
     }
 
-    fn multiplication(&mut self) -> AnyResult<ExprBox<'a>> {
+    fn multiplication(&mut self) -> ParseResult<ExprBox<'a>> {
         let mut expr = self.unary()?;
 
         if self.can_pair {
             while let Some(t) = self.scanner.peek() {
                 match t.token_type {
                     TokenType::Slash | TokenType::Star | TokenType::Mod | TokenType::ModAlias | TokenType::Div => {
-                        let token = self.scanner.next().unwrap();
+                        let token = self.consume_next();
                         let comments_and_newlines_between_op_and_r = self.get_newlines_and_comments();
                         let right = self.unary()?;
 
-                        expr = self.create_expr_box_no_comment(Expr::Binary {
-                            left: expr,
-                            operator: token,
-                            comments_and_newlines_between_op_and_r,
-                            right,
-                        });
+                        expr = self.create_expr_box_no_comment(
+                            expr.span.start,
+                            Expr::Binary {
+                                left: expr,
+                                operator: token,
+                                comments_and_newlines_between_op_and_r,
+                                right,
+                            },
+                        );
                     }
                     _ => break,
                 };
@@ -804,32 +991,38 @@ impl<'a> Parser<'a> {
         Ok(expr)
     }
 
-    fn unary(&mut self) -> AnyResult<ExprBox<'a>> {
+    fn unary(&mut self) -> ParseResult<ExprBox<'a>> {
         if self.can_pair {
             if let Some(t) = self.scanner.peek() {
                 match t.token_type {
                     TokenType::Bang | TokenType::Minus | TokenType::Plus | TokenType::Tilde | TokenType::NotAlias => {
-                        let t = self.scanner.next().unwrap();
+                        let t = self.consume_next();
                         let comments_and_newlines_between = self.get_newlines_and_comments();
                         let right = self.unary()?;
 
-                        return Ok(self.create_expr_box_no_comment(Expr::Unary {
-                            operator: t,
-                            comments_and_newlines_between,
-                            right,
-                        }));
+                        return Ok(self.create_expr_box_no_comment(
+                            t.span.start,
+                            Expr::Unary {
+                                operator: t,
+                                comments_and_newlines_between,
+                                right,
+                            },
+                        ));
                     }
 
                     TokenType::Incrementer | TokenType::Decrementer => {
-                        let t = self.scanner.next().unwrap();
+                        let t = self.consume_next();
                         let comments_and_newlines_between = self.get_newlines_and_comments();
                         let right = self.unary()?;
 
-                        return Ok(self.create_expr_box_no_comment(Expr::Unary {
-                            operator: t,
-                            comments_and_newlines_between,
-                            right,
-                        }));
+                        return Ok(self.create_expr_box_no_comment(
+                            t.span.start,
+                            Expr::Unary {
+                                operator: t,
+                                comments_and_newlines_between,
+                                right,
+                            },
+                        ));
                     }
 
                     _ => {}
@@ -840,35 +1033,41 @@ impl<'a> Parser<'a> {
         self.postfix()
     }
 
-    fn postfix(&mut self) -> AnyResult<ExprBox<'a>> {
+    fn postfix(&mut self) -> ParseResult<ExprBox<'a>> {
         let mut expr = self.call()?;
 
         if self.check_next_either(TokenType::Incrementer, TokenType::Decrementer) {
-            let t = self.scanner.next().unwrap();
+            let t = self.consume_next();
 
             let comments_and_newlines_between = self.get_newlines_and_comments();
-            expr = self.create_expr_box_no_comment(Expr::Postfix {
-                operator: t,
-                comments_and_newlines_between,
-                expr,
-            });
+            expr = self.create_expr_box_no_comment(
+                expr.span.start,
+                Expr::Postfix {
+                    operator: t,
+                    comments_and_newlines_between,
+                    expr,
+                },
+            );
         }
 
         Ok(expr)
     }
 
-    fn call(&mut self) -> AnyResult<ExprBox<'a>> {
+    fn call(&mut self) -> ParseResult<ExprBox<'a>> {
         let mut expression = self.primary()?;
 
         if self.check_next_consume(TokenType::LeftParen) {
             let comments_and_newlines_after_lparen = self.get_newlines_and_comments();
             let arguments = self.finish_call(TokenType::RightParen, TokenType::Comma)?;
 
-            expression = self.create_comment_expr_box(Expr::Call {
-                procedure_name: expression,
-                arguments,
-                comments_and_newlines_after_lparen,
-            });
+            expression = self.create_comment_expr_box(
+                expression.span.start,
+                Expr::Call {
+                    procedure_name: expression,
+                    arguments,
+                    comments_and_newlines_after_lparen,
+                },
+            );
         }
 
         while let Some(token) = self.scanner.peek() {
@@ -877,11 +1076,14 @@ impl<'a> Parser<'a> {
                     self.consume_next();
                     let comments_between = self.get_newlines_and_comments();
                     let instance_variable = self.call()?;
-                    expression = self.create_comment_expr_box(Expr::DotAccess {
-                        object_name: expression,
-                        comments_between,
-                        instance_variable,
-                    });
+                    expression = self.create_comment_expr_box(
+                        expression.span.start,
+                        Expr::DotAccess {
+                            object_name: expression,
+                            comments_between,
+                            instance_variable,
+                        },
+                    );
                 }
 
                 TokenType::LeftBracket
@@ -889,7 +1091,7 @@ impl<'a> Parser<'a> {
                 | TokenType::MapIndexer
                 | TokenType::ListIndexer
                 | TokenType::GridIndexer => {
-                    let access_type = self.scanner.next().unwrap();
+                    let access_type = self.consume_next();
                     let mut access_exprs = vec![];
 
                     while let Some(token) = self.scanner.peek() {
@@ -905,11 +1107,14 @@ impl<'a> Parser<'a> {
                     }
 
                     self.check_next_consume(TokenType::RightBracket);
-                    expression = self.create_comment_expr_box(Expr::DataStructureAccess {
-                        ds_name: expression,
-                        access_type,
-                        access_exprs,
-                    });
+                    expression = self.create_comment_expr_box(
+                        expression.span.start,
+                        Expr::DataStructureAccess {
+                            ds_name: expression,
+                            access_type,
+                            access_exprs,
+                        },
+                    );
                 }
 
                 _ => break,
@@ -919,37 +1124,47 @@ impl<'a> Parser<'a> {
         Ok(expression)
     }
 
-    fn primary(&mut self) -> AnyResult<ExprBox<'a>> {
+    fn primary(&mut self) -> ParseResult<ExprBox<'a>> {
         if let Some(t) = self.scanner.peek() {
+            let start = t.span.start;
             let output = match t.token_type {
                 TokenType::Number(_) | TokenType::String(_) => {
                     let t = self.consume_next();
                     let comments = self.get_newlines_and_comments();
-                    self.create_expr_box_no_comment(Expr::Literal {
-                        literal_token: t,
-                        comments,
-                    })
+                    self.create_expr_box_no_comment(
+                        start,
+                        Expr::Literal {
+                            literal_token: t,
+                            comments,
+                        },
+                    )
                 }
                 TokenType::NumberStartDot(_) => {
                     let t = self.consume_next();
                     let comments = self.get_newlines_and_comments();
-                    self.create_expr_box_no_comment(Expr::NumberStartDot {
-                        literal_token: t,
-                        comments,
-                    })
+                    self.create_expr_box_no_comment(
+                        start,
+                        Expr::NumberStartDot {
+                            literal_token: t,
+                            comments,
+                        },
+                    )
                 }
                 TokenType::NumberEndDot(_) => {
                     let t = self.consume_next();
                     let comments = self.get_newlines_and_comments();
-                    self.create_expr_box_no_comment(Expr::NumberEndDot {
-                        literal_token: t,
-                        comments,
-                    })
+                    self.create_expr_box_no_comment(
+                        start,
+                        Expr::NumberEndDot {
+                            literal_token: t,
+                            comments,
+                        },
+                    )
                 }
                 TokenType::Identifier(_) => {
                     let t = self.consume_next();
                     let comments = self.get_newlines_and_comments();
-                    self.create_expr_box_no_comment(Expr::Identifier { name: t, comments })
+                    self.create_expr_box_no_comment(start, Expr::Identifier { name: t, comments })
                 }
                 TokenType::LeftParen => {
                     self.consume_next();
@@ -963,11 +1178,14 @@ impl<'a> Parser<'a> {
 
                     let comments_and_newlines_after_rparen = self.get_newlines_and_comments();
 
-                    self.create_expr_box_no_comment(Expr::Grouping {
-                        expressions,
-                        comments_and_newlines_after_lparen,
-                        comments_and_newlines_after_rparen,
-                    })
+                    self.create_expr_box_no_comment(
+                        start,
+                        Expr::Grouping {
+                            expressions,
+                            comments_and_newlines_after_lparen,
+                            comments_and_newlines_after_rparen,
+                        },
+                    )
                 }
 
                 TokenType::LeftBracket => {
@@ -975,48 +1193,59 @@ impl<'a> Parser<'a> {
                     let comments_and_newlines_after_lbracket = self.get_newlines_and_comments();
                     let arguments = self.finish_call(TokenType::RightBracket, TokenType::Comma)?;
 
-                    self.create_expr_box_no_comment(Expr::ArrayLiteral {
-                        comments_and_newlines_after_lbracket,
-                        arguments,
-                    })
+                    self.create_expr_box_no_comment(
+                        start,
+                        Expr::ArrayLiteral {
+                            comments_and_newlines_after_lbracket,
+                            arguments,
+                        },
+                    )
                 }
 
                 TokenType::Newline(_) => {
                     self.consume_next();
                     self.can_pair = false;
-                    self.create_expr_box_no_comment(Expr::Newline)
+                    self.create_expr_box_no_comment(start, Expr::Newline)
                 }
                 TokenType::Comment(_) => {
                     let comment = self.consume_next();
                     self.can_pair = false;
-                    self.create_expr_box_no_comment(Expr::Comment { comment })
+                    self.create_expr_box_no_comment(start, Expr::Comment { comment })
                 }
                 TokenType::MultilineComment(_) => {
                     let multiline_comment = self.consume_next();
                     self.can_pair = false;
-                    self.create_expr_box_no_comment(Expr::MultilineComment { multiline_comment })
+                    self.create_expr_box_no_comment(start, Expr::MultilineComment { multiline_comment })
                 }
                 _ => {
                     let literal_token = self.consume_next();
                     if self.allow_unidentified == false {
-                        anyhow::bail!("Error parsing {}", literal_token);
+                        let message = match literal_token.token_type {
+                            TokenType::UnidentifiedInput(_) => {
+                                format!("unrecognized input `{}`", lexeme(&literal_token))
+                            }
+                            _ => format!("expected an expression, found `{}`", lexeme(&literal_token)),
+                        };
+                        return Err(Diagnostic::error_at(&self.line_index, &literal_token, message));
                     }
 
-                    self.create_comment_expr_box(Expr::UnidentifiedAsLiteral { literal_token })
+                    self.create_comment_expr_box(start, Expr::UnidentifiedAsLiteral { literal_token })
                 }
             };
 
             return Ok(output);
         }
 
-        anyhow::bail!("Unexpected end!");
+        let diagnostic = Diagnostic::error_at_end(&self.line_index, "unexpected end of file".to_owned())
+            .with_help("a statement or expression was left unfinished");
+        Err(diagnostic)
     }
 
     fn finish_call(
         &mut self,
         end_token_type: TokenType,
         delimiter_type: TokenType,
-    ) -> AnyResult<DelimitedLines<'a, ExprBox<'a>>> {
+    ) -> ParseResult<DelimitedLines<'a, ExprBox<'a>>> {
         let mut arguments = Vec::new();
 
         let mut end_delimiter = true;
@@ -1109,20 +1338,69 @@ impl<'a> Parser<'a> {
     }
 
     fn consume_next(&mut self) -> Token<'a> {
-        self.scanner.next().unwrap()
+        let token = self.scanner.next().unwrap();
+        self.last_end = token.span.end;
+        token
     }
 
-    fn create_comment_expr_box(&mut self, expr: Expr<'a>) -> ExprBox<'a> {
+    /// Boxes an expression which started at `start` and ends with the last token we consumed.
+    fn create_comment_expr_box(&mut self, start: u32, expr: Expr<'a>) -> ExprBox<'a> {
+        let span = start..self.last_end.max(start);
         Box::new(ExprBoxInterior {
             expr,
             trailing_comments: self.get_newlines_and_comments(),
+            span,
         })
     }
 
-    fn create_expr_box_no_comment(&self, expr: Expr<'a>) -> ExprBox<'a> {
+    fn create_expr_box_no_comment(&self, start: u32, expr: Expr<'a>) -> ExprBox<'a> {
         Box::new(ExprBoxInterior {
             expr,
             trailing_comments: None,
+            span: start..self.last_end.max(start),
         })
     }
 }
+
+/// How a token was written, for error messages.
+fn lexeme<'a>(token: &'a Token<'a>) -> String {
+    Printer::get_token_name(&token.token_type).escape_debug().to_string()
+}
+
+/// Whether a token can only be the start of a statement, so we can start parsing again there.
+fn opens_bracket(token_type: TokenType) -> bool {
+    matches!(
+        token_type,
+        TokenType::LeftParen
+            | TokenType::LeftBracket
+            | TokenType::ListIndexer
+            | TokenType::MapIndexer
+            | TokenType::GridIndexer
+            | TokenType::ArrayIndexer
+    )
+}
+
+fn starts_statement(token_type: TokenType) -> bool {
+    matches!(
+        token_type,
+        TokenType::Var
+            | TokenType::GlobalVar
+            | TokenType::If
+            | TokenType::Return
+            | TokenType::For
+            | TokenType::Repeat
+            | TokenType::With
+            | TokenType::While
+            | TokenType::Do
+            | TokenType::Switch
+            | TokenType::Case
+            | TokenType::DefaultCase
+            | TokenType::Break
+            | TokenType::Exit
+            | TokenType::Enum
+            | TokenType::Define
+            | TokenType::Macro(_)
+            | TokenType::RegionBegin(_)
+            | TokenType::RegionEnd(_)
+    )
+}
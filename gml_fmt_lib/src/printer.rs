@@ -2,7 +2,6 @@ use super::expressions::*;
 use super::lex_token::{Token, TokenType};
 use super::statements::*;
 use super::LangConfig;
-use bitflags;
 
 type StmtBox<'a> = Box<StatementWrapper<'a>>;
 
@@ -55,7 +54,7 @@ impl<'a> Printer<'a> {
         output
     }
 
-    pub fn autoformat(mut self, ast: &'a [StmtBox<'a>]) -> Printer {
+    pub fn autoformat(mut self, ast: &'a [StmtBox<'a>]) -> Printer<'a> {
         for this_statement in ast {
             self.print_statement(this_statement);
         }
@@ -107,7 +106,7 @@ impl<'a> Printer<'a> {
                 let mut iter = var_decl_list.lines.iter().peekable();
                 while let Some(delimited_var) = iter.next() {
                     if let Some(var_token) = &delimited_var.expr.say_var {
-                        self.print_token(&var_token, true);
+                        self.print_token(var_token, true);
 
                         if let Some(comments) = &delimited_var.expr.say_var_comments {
                             let did_move = self.print_comments_and_newlines(
@@ -144,7 +143,7 @@ impl<'a> Printer<'a> {
                         }
                     };
 
-                    if let Some(_) = &delimited_var.trailing_comment {
+                    if delimited_var.trailing_comment.is_some() {
                         self.allow_user_indentation();
                         let did_newlines = self.print_comments_and_newlines(
                             &delimited_var.trailing_comment,
@@ -227,7 +226,7 @@ impl<'a> Printer<'a> {
                 self.print(RBRACE, false);
                 self.print_semicolon(stmt.has_semicolon);
             }
-            Statement::ExpresssionStatement { expression } => {
+            Statement::ExpressionStatement { expression } => {
                 // let final_newlines: Option<&CommentsAndNewlines> = {
                 //     if stmt.has_semicolon {
                 //         None
@@ -292,11 +291,9 @@ impl<'a> Printer<'a> {
 
                 for stmt in statements {
                     self.print_statement(stmt);
-                    if did_newline {
-                        if self.on_whitespace_line() == false {
-                            self.print_newline(IndentationMove::Stay);
-                            self.do_not_print_single_newline_statement = true;
-                        }
+                    if did_newline && self.on_whitespace_line() == false {
+                        self.print_newline(IndentationMove::Stay);
+                        self.do_not_print_single_newline_statement = true;
                     }
                 }
 
@@ -595,6 +592,9 @@ impl<'a> Printer<'a> {
                 self.print_token(comment, false);
                 self.backspace();
             }
+            Statement::Unknown { source } => {
+                self.print(source, false);
+            }
             Statement::Define {
                 comments_after_control_word,
                 script_name,
@@ -634,7 +634,8 @@ impl<'a> Printer<'a> {
                 | Statement::MultilineComment { .. }
                 | Statement::RegionBegin { .. }
                 | Statement::RegionEnd { .. }
-                | Statement::Macro { .. } => {}
+                | Statement::Macro { .. }
+                | Statement::Unknown { .. } => {}
 
                 _ => {
                     // we do this so we *always* print a newline.
@@ -653,7 +654,7 @@ impl<'a> Printer<'a> {
                                 newlines = usize::max(newlines, 1);
                             }
                         }
-                        if last_entry != RBRACE && last_entry != SEMICOLON {}
+
                         for _ in 0..newlines {
                             self.print_newline(IndentationMove::Stay);
                         }
@@ -730,10 +731,7 @@ impl<'a> Printer<'a> {
                     }
                 }
                 self.print(RPAREN, true);
-                let instructions = match self.group_instructions.pop() {
-                    Some(instruction) => instruction,
-                    None => Default::default(),
-                };
+                let instructions = self.group_instructions.pop().unwrap_or_default();
 
                 self.print_comments_and_newlines(
                     comments_and_newlines_after_rparen,
@@ -767,7 +765,7 @@ impl<'a> Printer<'a> {
                 literal_token,
                 comments,
             } => {
-                self.print_token(&literal_token, true);
+                self.print_token(literal_token, true);
                 self.print_comments_and_newlines(
                     comments,
                     CommentAndNewlinesInstruction::new(IndentationMove::Stay, LeadingNewlines::All),
@@ -779,7 +777,7 @@ impl<'a> Printer<'a> {
                 comments,
             } => {
                 self.print("0", false);
-                self.print_token(&literal_token, true);
+                self.print_token(literal_token, true);
                 self.print_comments_and_newlines(
                     comments,
                     CommentAndNewlinesInstruction::new(IndentationMove::Stay, LeadingNewlines::All),
@@ -790,7 +788,7 @@ impl<'a> Printer<'a> {
                 literal_token,
                 comments,
             } => {
-                self.print_token(&literal_token, false);
+                self.print_token(literal_token, false);
                 self.print("0", true);
                 self.print_comments_and_newlines(
                     comments,
@@ -803,7 +801,7 @@ impl<'a> Printer<'a> {
                 comments_and_newlines_between,
                 right,
             } => {
-                self.print_token(&operator, operator.token_type == TokenType::NotAlias);
+                self.print_token(operator, operator.token_type == TokenType::NotAlias);
                 self.print_comments_and_newlines(
                     comments_and_newlines_between,
                     CommentAndNewlinesInstruction::new(IndentationMove::Stay, LeadingNewlines::All),
@@ -817,7 +815,7 @@ impl<'a> Printer<'a> {
             } => {
                 self.print_expr(expr);
                 self.backspace();
-                self.print_token(&operator, true);
+                self.print_token(operator, true);
                 self.print_comments_and_newlines(
                     comments_and_newlines_between,
                     CommentAndNewlinesInstruction::new(IndentationMove::Stay, LeadingNewlines::All),
@@ -830,7 +828,7 @@ impl<'a> Printer<'a> {
                 right,
             } => {
                 self.print_expr(left);
-                self.print_token(&operator, true);
+                self.print_token(operator, true);
                 self.print_comments_and_newlines(
                     comments_and_newlines_between_op_and_r,
                     CommentAndNewlinesInstruction::new(IndentationMove::Stay, LeadingNewlines::All),
@@ -881,9 +879,9 @@ impl<'a> Printer<'a> {
                 self.print_expr(ds_name);
                 self.backspace();
 
-                self.print_token(&access_type, access_type.token_type != TokenType::LeftBracket);
+                self.print_token(access_type, access_type.token_type != TokenType::LeftBracket);
 
-                let mut iter = access_exprs.into_iter().peekable();
+                let mut iter = access_exprs.iter().peekable();
                 while let Some((comments, expr)) = iter.next() {
                     self.allow_user_indentation();
                     self.print_comments_and_newlines(
@@ -894,7 +892,7 @@ impl<'a> Printer<'a> {
                     self.rewind_user_indentation();
                     self.backspace();
 
-                    if let Some(_) = iter.peek() {
+                    if iter.peek().is_some() {
                         self.print(COMMA, true);
                     }
                 }
@@ -956,7 +954,7 @@ impl<'a> Printer<'a> {
             }
 
             Expr::UnidentifiedAsLiteral { literal_token } => {
-                self.print_token(&literal_token, true);
+                self.print_token(literal_token, true);
             }
         }
 
@@ -1114,7 +1112,7 @@ impl<'a> Printer<'a> {
     }
 
     fn print_newline(&mut self, indentation_move: IndentationMove) {
-        if self.output.len() == 0 || self.prev_line_was_whitespace() {
+        if self.output.is_empty() || self.prev_line_was_whitespace() {
             return;
         }
         self.backspace();
@@ -1179,13 +1177,13 @@ impl<'a> Printer<'a> {
         instructions: CommentAndNewlinesInstruction,
     ) -> bool {
         if let Some(vec) = vec {
-            if vec.len() == 0 || (Printer::only_newlines(&vec) && instructions.respect_user_newline == false) {
+            if vec.is_empty() || (Printer::only_newlines(vec) && instructions.respect_user_newline == false) {
                 return false;
             }
             let mut did_move = false;
             let mut ignore_newline = instructions.leading_newlines != LeadingNewlines::All;
 
-            let mut iter = vec.into_iter().peekable();
+            let mut iter = vec.iter().peekable();
             while let Some(this_one) = iter.next() {
                 match this_one.token_type {
                     TokenType::Newline(user_indentation) => {
@@ -1231,7 +1229,7 @@ impl<'a> Printer<'a> {
                             self.do_not_need_semicolon.push(());
                         }
                         self.ensure_space();
-                        self.print_token(&this_one, false);
+                        self.print_token(this_one, false);
                         ignore_newline = false;
                     }
 
@@ -1240,7 +1238,7 @@ impl<'a> Printer<'a> {
                             self.do_not_need_semicolon.push(())
                         }
                         self.ensure_space();
-                        self.print_token(&this_one, true);
+                        self.print_token(this_one, true);
                         ignore_newline = false;
                     }
 
@@ -1249,19 +1247,16 @@ impl<'a> Printer<'a> {
                             self.do_not_need_semicolon.push(())
                         }
                         self.ensure_space();
-                        self.print_token(&this_one, true);
+                        self.print_token(this_one, true);
                         ignore_newline = false;
                     }
 
+                    // Only trivia should end up here, but anything else is still printed as written.
                     _ => {
-                        println!(
-                            "Printing {} which isn't newline, comment, or region in a comment_newline section...",
-                            this_one
-                        );
                         if instructions.trailing_comment {
                             self.do_not_need_semicolon.push(())
                         }
-                        self.print_token(&this_one, true);
+                        self.print_token(this_one, true);
                     }
                 }
             }
@@ -1384,7 +1379,7 @@ impl<'a> Printer<'a> {
             self.print_expr(&delimited_line.expr);
             self.backspace();
 
-            let at_end = if let Some(_) = iter.peek() {
+            let at_end = if iter.peek().is_some() {
                 self.print(delimiter, true);
                 false
             } else {
@@ -1394,7 +1389,7 @@ impl<'a> Printer<'a> {
                 true
             };
 
-            if let Some(_) = &delimited_line.trailing_comment {
+            if delimited_line.trailing_comment.is_some() {
                 let did_newlines = self.print_comments_and_newlines(
                     &delimited_line.trailing_comment,
                     CommentAndNewlinesInstruction::new_respect_users(IndentationMove::Stay, LeadingNewlines::All),
@@ -1491,11 +1486,7 @@ impl GroupInstruction {
     }
 
     fn force_respect(&self) -> bool {
-        if let Some(ret) = self.force_respect {
-            ret
-        } else {
-            false
-        }
+        self.force_respect.unwrap_or_default()
     }
 }
 
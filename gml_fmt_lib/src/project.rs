@@ -0,0 +1,326 @@
+//! Reads a GameMaker Studio 2 project, so we format the code the project uses
+//! rather than every `.gml` file lying around in its folder.
+//!
+//! The `.yyp` lists each resource and the `.yy` file describing it, and each
+//! `.yy` says which pieces of code the resource has. We read both the 2.3 layout
+//! and the older 2.2 one, which named things differently.
+
+use super::config::{ConfigError, TraversalError};
+use serde::Deserialize;
+use serde_json::Value;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The kinds of resource which hold GML.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum ResourceKind {
+    #[serde(rename = "scripts")]
+    Script,
+    #[serde(rename = "objects")]
+    Object,
+    #[serde(rename = "rooms")]
+    Room,
+    #[serde(rename = "timelines")]
+    Timeline,
+}
+
+impl ResourceKind {
+    /// Reads the plural the CLI and `gml_fmt.toml` use, such as `scripts`.
+    pub fn from_plural(name: &str) -> Option<ResourceKind> {
+        match name {
+            "scripts" => Some(ResourceKind::Script),
+            "objects" => Some(ResourceKind::Object),
+            "rooms" => Some(ResourceKind::Room),
+            "timelines" => Some(ResourceKind::Timeline),
+            _ => None,
+        }
+    }
+
+    /// Every resource lives in a folder named for its kind, in both layouts.
+    fn from_folder(folder: &str) -> Option<ResourceKind> {
+        ResourceKind::from_plural(&folder.to_ascii_lowercase())
+    }
+}
+
+/// One `.gml` file the project uses.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct ProjectFile {
+    pub path: PathBuf,
+    /// What GameMaker calls it, such as `obj_player: Step`.
+    pub name: String,
+    pub kind: ResourceKind,
+}
+
+/// The `.yyp` directly inside `dir`, if there is one. If there are several,
+/// which GameMaker never makes, we take the first by name.
+pub(crate) fn find_project_file(dir: &Path) -> Option<PathBuf> {
+    let mut projects: Vec<_> = fs::read_dir(dir)
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|extension| extension == "yyp") && path.is_file())
+        .collect();
+    projects.sort();
+    projects.into_iter().next()
+}
+
+/// Every `.gml` file the project at `yyp` references, in the order the `.yyp`
+/// lists their resources. A resource whose `.yy` we can't read is skipped and
+/// given back as an error alongside the rest, just as walking skips what it can't read.
+pub(crate) fn project_files(yyp: &Path) -> Result<(Vec<ProjectFile>, Vec<TraversalError>), ConfigError> {
+    let project = read_json(yyp).map_err(|message| ConfigError::Unreadable {
+        path: yyp.to_path_buf(),
+        message,
+    })?;
+    let root = yyp.parent().unwrap_or_else(|| Path::new(""));
+    let mut files = Vec::new();
+    let mut errors = Vec::new();
+
+    for resource in project["resources"].as_array().into_iter().flatten() {
+        // 2.3 has `id: { name, path }`, and 2.2 has `Value: { resourcePath }`.
+        let yy = resource["id"]["path"]
+            .as_str()
+            .or_else(|| resource["Value"]["resourcePath"].as_str())
+            .map(|yy| yy.replace('\\', "/"));
+        let yy = match yy {
+            Some(yy) => yy,
+            None => continue,
+        };
+        let kind = match yy.split('/').next().and_then(ResourceKind::from_folder) {
+            Some(kind) => kind,
+            None => continue,
+        };
+
+        let yy_path = root.join(&yy);
+        let description = match read_json(&yy_path) {
+            Ok(description) => description,
+            Err(message) => {
+                errors.push(TraversalError::Unreadable { path: yy_path, message });
+                continue;
+            }
+        };
+        let name = resource["id"]["name"]
+            .as_str()
+            .or_else(|| description["name"].as_str())
+            .map(String::from)
+            .or_else(|| yy_path.file_stem().map(|stem| stem.to_string_lossy().into_owned()))
+            .unwrap_or_default();
+        let dir = yy_path.parent().unwrap_or(root);
+
+        let code = match kind {
+            ResourceKind::Script => vec![(format!("{}.gml", name), name.clone())],
+            ResourceKind::Object => object_code(&name, &description),
+            ResourceKind::Room => room_code(&name, &description),
+            ResourceKind::Timeline => timeline_code(&name, &description),
+        };
+
+        // The `.yy` can list code which was never written, such as an empty event.
+        for (file_name, code_name) in code {
+            let path = dir.join(file_name);
+            if path.is_file() {
+                files.push(ProjectFile {
+                    path,
+                    name: code_name,
+                    kind,
+                });
+            }
+        }
+    }
+
+    Ok((files, errors))
+}
+
+/// Each event's file name and what GameMaker calls it.
+fn object_code(object: &str, description: &Value) -> Vec<(String, String)> {
+    let mut code = Vec::new();
+
+    for event in description["eventList"].as_array().into_iter().flatten() {
+        let event_type = event["eventType"].as_u64().or_else(|| event["eventtype"].as_u64());
+        let number = event["eventNum"]
+            .as_u64()
+            .or_else(|| event["enumb"].as_u64())
+            .unwrap_or_default();
+        let event_type = match event_type {
+            Some(event_type) => event_type,
+            None => continue,
+        };
+
+        // 2.3 names the other object, and 2.2 gives its id.
+        let collision = event["collisionObjectId"]["name"]
+            .as_str()
+            .or_else(|| event["collisionObjectId"].as_str())
+            .unwrap_or_default();
+
+        if let Some((file_stem, event_name)) = event_names(event_type, number, collision) {
+            code.push((format!("{}.gml", file_stem), format!("{}: {}", object, event_name)));
+        }
+    }
+
+    code
+}
+
+/// The room's creation code, then each instance's.
+fn room_code(room: &str, description: &Value) -> Vec<(String, String)> {
+    let mut code = Vec::new();
+
+    if description["creationCodeFile"]
+        .as_str()
+        .is_some_and(|file| file.is_empty() == false)
+    {
+        code.push(("RoomCreationCode.gml".to_owned(), format!("{}: Creation Code", room)));
+    }
+
+    let mut instances = Vec::new();
+    find_instances(&description["layers"], &mut instances);
+    // 2.2 kept instances in a list of their own.
+    find_instances(&description["instances"], &mut instances);
+
+    for instance in instances {
+        code.push((
+            format!("InstanceCreationCode_{}.gml", instance),
+            format!("{}: {} Creation Code", room, instance),
+        ));
+    }
+
+    code
+}
+
+/// The names of instances with creation code. 2.3 nests layers inside layers.
+fn find_instances(value: &Value, instances: &mut Vec<String>) {
+    match value {
+        Value::Array(values) => values.iter().for_each(|value| find_instances(value, instances)),
+        Value::Object(object) => {
+            let has_code = object.get("hasCreationCode").and_then(Value::as_bool) == Some(true)
+                || object
+                    .get("creationCodeFile")
+                    .and_then(Value::as_str)
+                    .is_some_and(|file| file.is_empty() == false);
+
+            match object.get("name").and_then(Value::as_str) {
+                Some(name) if has_code && instances.iter().any(|known| known == name) == false => {
+                    instances.push(name.to_owned())
+                }
+                _ => {}
+            }
+
+            for key in ["layers", "instances"] {
+                if let Some(children) = object.get(key) {
+                    find_instances(children, instances);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+fn timeline_code(timeline: &str, description: &Value) -> Vec<(String, String)> {
+    description["momentList"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|moment| moment["moment"].as_u64())
+        .map(|moment| {
+            (
+                format!("moment_{}.gml", moment),
+                format!("{}: Moment {}", timeline, moment),
+            )
+        })
+        .collect()
+}
+
+/// The file stem GameMaker saves an event's code under, and the name its IDE shows.
+fn event_names(event_type: u64, number: u64, collision: &str) -> Option<(String, String)> {
+    let type_name = match event_type {
+        0 => "Create",
+        1 => "Destroy",
+        2 => "Alarm",
+        3 => "Step",
+        4 => "Collision",
+        5 => "Keyboard",
+        6 => "Mouse",
+        7 => "Other",
+        8 => "Draw",
+        9 => "KeyPress",
+        10 => "KeyRelease",
+        11 => "Trigger",
+        12 => "CleanUp",
+        13 => "Gesture",
+        _ => return None,
+    };
+
+    if event_type == 4 {
+        return Some((
+            format!("Collision_{}", collision),
+            format!("Collision with {}", collision),
+        ));
+    }
+
+    let event_name = match (event_type, number) {
+        (0, _) | (1, _) => type_name.to_owned(),
+        (12, _) => "Clean Up".to_owned(),
+        (3, 0) => "Step".to_owned(),
+        (3, 1) => "Begin Step".to_owned(),
+        (3, 2) => "End Step".to_owned(),
+        (7, 0) => "Outside Room".to_owned(),
+        (7, 1) => "Intersect Boundary".to_owned(),
+        (7, 2) => "Game Start".to_owned(),
+        (7, 3) => "Game End".to_owned(),
+        (7, 4) => "Room Start".to_owned(),
+        (7, 5) => "Room End".to_owned(),
+        (7, 7) => "Animation End".to_owned(),
+        (7, 8) => "Path Ended".to_owned(),
+        (7, 10..=25) => format!("User Event {}", number - 10),
+        (8, 0) => "Draw".to_owned(),
+        (8, 64) => "Draw GUI".to_owned(),
+        (8, 65) => "Window Resize".to_owned(),
+        (8, 72) => "Draw Begin".to_owned(),
+        (8, 73) => "Draw End".to_owned(),
+        (8, 74) => "Draw GUI Begin".to_owned(),
+        (8, 75) => "Draw GUI End".to_owned(),
+        (8, 76) => "Pre-Draw".to_owned(),
+        (8, 77) => "Post-Draw".to_owned(),
+        (9, _) => format!("Key Press {}", number),
+        (10, _) => format!("Key Release {}", number),
+        _ => format!("{} {}", type_name, number),
+    };
+
+    Some((format!("{}_{}", type_name, number), event_name))
+}
+
+/// Reads a `.yyp` or `.yy`, or gives back why we couldn't.
+fn read_json(path: &Path) -> Result<Value, String> {
+    let contents = fs::read_to_string(path).map_err(|e| e.to_string())?;
+    serde_json::from_str(&strip_trailing_commas(&contents)).map_err(|e| e.to_string())
+}
+
+/// GameMaker 2.3 writes a comma after the last item of every list and object,
+/// which JSON doesn't allow, so we drop any comma followed only by whitespace
+/// and a closing bracket. Commas inside strings are left alone.
+fn strip_trailing_commas(json: &str) -> String {
+    let mut output = String::with_capacity(json.len());
+    let mut chars = json.char_indices().peekable();
+    let mut in_string = false;
+
+    while let Some((index, c)) = chars.next() {
+        match c {
+            '"' => in_string = in_string == false,
+            '\\' if in_string => {
+                output.push(c);
+                if let Some((_, escaped)) = chars.next() {
+                    output.push(escaped);
+                }
+                continue;
+            }
+            ',' if in_string == false => {
+                let rest = json[index + 1..].trim_start();
+                if rest.starts_with('}') || rest.starts_with(']') {
+                    continue;
+                }
+            }
+            _ => {}
+        }
+        output.push(c);
+    }
+
+    output
+}
@@ -0,0 +1,152 @@
+use super::diagnostic::Diagnostic;
+use super::error::Error;
+use serde::Serialize;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// What happened to every file in a run, in the order the files were given.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Report {
+    pub files: Vec<FileReport>,
+    pub totals: Totals,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FileReport {
+    pub path: PathBuf,
+    /// What GameMaker calls the file, such as `obj_player: Step`, when it came from a `.yyp`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub resource: Option<String>,
+    pub status: FileStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<FileError>,
+    pub bytes_before: usize,
+    pub bytes_after: usize,
+    pub time_ms: f64,
+    /// Every problem we found parsing the file. `error` is the first of them.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub diagnostics: Vec<Diagnostic>,
+    /// The hash of the file's contents, if they're known to be formatted now.
+    #[serde(skip)]
+    pub(crate) formatted_hash: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FileStatus {
+    /// The file was already formatted.
+    Unchanged,
+    /// Formatting changed the file. With `-n`, this is what *would* have changed.
+    Reformatted,
+    /// The file couldn't be parsed, and nothing we could parse needed formatting, so
+    /// it was left alone.
+    ParseError,
+    /// Some statements couldn't be parsed and were kept exactly as they were, but
+    /// formatting the rest changed the file. With `-n`, this is what *would* have changed.
+    FormattedWithErrors,
+    /// The file asked to be ignored with `// @gml_fmt ignore`.
+    Ignored,
+    /// The file couldn't be read or written.
+    Failed,
+    /// Formatting would have changed more than whitespace, so the file was left alone.
+    VerificationFailed,
+}
+
+/// Lines and columns start at 1, for people and dashboards rather than for tokens.
+#[derive(Debug, Clone, Serialize)]
+pub struct FileError {
+    pub message: String,
+    pub line: Option<u32>,
+    pub column: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub help: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Totals {
+    pub files: usize,
+    pub unchanged: usize,
+    pub reformatted: usize,
+    pub parse_errors: usize,
+    pub formatted_with_errors: usize,
+    pub ignored: usize,
+    pub failed: usize,
+    pub verification_failures: usize,
+    pub bytes_before: usize,
+    pub bytes_after: usize,
+    pub time_ms: f64,
+}
+
+impl FileReport {
+    pub(crate) fn new(path: PathBuf) -> FileReport {
+        FileReport {
+            path,
+            resource: None,
+            status: FileStatus::Unchanged,
+            error: None,
+            bytes_before: 0,
+            bytes_after: 0,
+            time_ms: 0.0,
+            diagnostics: Vec::new(),
+            formatted_hash: None,
+        }
+    }
+}
+
+impl FileError {
+    pub(crate) fn new(error: &Error) -> FileError {
+        let message = match error {
+            Error::Parse { diagnostics, .. } => return FileError::from_diagnostic(&diagnostics[0]),
+            Error::Verification { diagnostic, .. } => return FileError::from_diagnostic(diagnostic),
+            // The report gives the path already.
+            Error::Io { source, .. } => source.to_string(),
+            other => other.to_string(),
+        };
+
+        FileError {
+            message,
+            line: None,
+            column: None,
+            help: None,
+        }
+    }
+
+    pub(crate) fn from_diagnostic(diagnostic: &Diagnostic) -> FileError {
+        FileError {
+            message: diagnostic.message.clone(),
+            line: Some(diagnostic.line),
+            column: Some(diagnostic.column),
+            help: diagnostic.help.clone(),
+        }
+    }
+}
+
+impl Report {
+    pub(crate) fn new(files: Vec<FileReport>, elapsed: Duration) -> Report {
+        let mut totals = Totals {
+            files: files.len(),
+            time_ms: millis(elapsed),
+            ..Totals::default()
+        };
+
+        for file in &files {
+            match file.status {
+                FileStatus::Unchanged => totals.unchanged += 1,
+                FileStatus::Reformatted => totals.reformatted += 1,
+                FileStatus::ParseError => totals.parse_errors += 1,
+                FileStatus::FormattedWithErrors => totals.formatted_with_errors += 1,
+                FileStatus::Ignored => totals.ignored += 1,
+                FileStatus::Failed => totals.failed += 1,
+                FileStatus::VerificationFailed => totals.verification_failures += 1,
+            }
+            totals.bytes_before += file.bytes_before;
+            totals.bytes_after += file.bytes_after;
+        }
+
+        Report { files, totals }
+    }
+}
+
+pub(crate) fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
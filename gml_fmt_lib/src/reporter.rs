@@ -0,0 +1,73 @@
+use super::error::Error;
+use super::Formatted;
+use std::path::Path;
+
+/// Why we left a file alone without formatting it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum SkipReason {
+    /// The file asked to be ignored with `// @gml_fmt ignore`.
+    Ignored,
+    /// The cache says the file is already formatted.
+    Cached,
+    /// git has nothing staged for the file.
+    NotStaged,
+}
+
+/// Hears what happens to each file as we format it, so the caller decides what to
+/// show, if anything. The library itself never prints. Files are reported one at a
+/// time, in the order they were given, and every method does nothing by default.
+pub trait Reporter {
+    /// We're about to format `path`, whose contents are `source`.
+    fn on_file_start(&mut self, _path: &Path, _source: &str) {}
+
+    /// We formatted `path`. Anything we couldn't parse was copied into the output as it
+    /// was, and is in `formatted.diagnostics`. `ast` is the syntax tree, printed with
+    /// `{:#?}`, when `PrintFlags::LOG_AST` asked for it.
+    fn on_formatted(&mut self, _path: &Path, _source: &str, _formatted: &Formatted, _ast: Option<&str>) {}
+
+    /// `path` couldn't be formatted, or what we formatted couldn't be written. `source`
+    /// is the file's contents, if we got as far as reading them.
+    fn on_error(&mut self, _path: &Path, _source: Option<&str>, _error: &Error) {}
+
+    /// We left `path` alone without formatting it.
+    fn on_skipped(&mut self, _path: &Path, _reason: SkipReason) {}
+}
+
+/// A `Reporter` which ignores everything, for callers who only want the results.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Silent;
+
+impl Reporter for Silent {}
+
+/// Something which happened to a file, held until every file before it has been reported.
+pub(crate) enum Event {
+    Start,
+    Formatted { formatted: Formatted, ast: Option<String> },
+    Error(Error),
+    Skipped(SkipReason),
+}
+
+/// Everything one file has to tell the reporter.
+#[derive(Default)]
+pub(crate) struct FileEvents {
+    pub source: Option<String>,
+    pub events: Vec<Event>,
+}
+
+impl FileEvents {
+    pub fn replay(&self, path: &Path, reporter: &mut dyn Reporter) {
+        let source = self.source.as_deref();
+
+        for event in &self.events {
+            match event {
+                Event::Start => reporter.on_file_start(path, source.unwrap_or_default()),
+                Event::Formatted { formatted, ast } => {
+                    reporter.on_formatted(path, source.unwrap_or_default(), formatted, ast.as_deref())
+                }
+                Event::Error(e) => reporter.on_error(path, source, e),
+                Event::Skipped(reason) => reporter.on_skipped(path, *reason),
+            }
+        }
+    }
+}
@@ -2,6 +2,7 @@ use super::lex_token::*;
 use fnv::FnvHashMap;
 use once_cell::sync::Lazy;
 use std::iter::Peekable;
+use std::ops::Range;
 use std::str::CharIndices;
 
 static KEYWORD_MAP: Lazy<FnvHashMap<&'static str, TokenType>> = Lazy::new(|| {
@@ -32,10 +33,14 @@ static KEYWORD_MAP: Lazy<FnvHashMap<&'static str, TokenType>> = Lazy::new(|| {
     map
 });
 
+/// Splits a source into `Token`s as an iterator. Whitespace is skipped, except for
+/// newlines, which become `Newline` tokens holding the next line's indentation.
+#[derive(Clone)]
 pub struct Scanner<'a> {
     input: &'a str,
     line_number: u32,
     column_number: u32,
+    token_start: usize,
     iter: Peekable<CharIndices<'a>>,
 }
 
@@ -45,12 +50,14 @@ impl<'a> Scanner<'a> {
             input,
             line_number: 0,
             column_number: 0,
+            token_start: 0,
             iter: input.char_indices().peekable(),
         }
     }
 
     pub fn lex_input(&mut self) -> Option<Token<'a>> {
         while let Some((i, c)) = self.iter.next() {
+            self.token_start = i;
             let found_token = match c {
                 '(' => self.add_simple_token(TokenType::LeftParen),
                 ')' => self.add_simple_token(TokenType::RightParen),
@@ -310,7 +317,12 @@ impl<'a> Scanner<'a> {
                         Some(macro_directive) => {
                             if is_multiline {
                                 self.column_number += (current - last_column_break) as u32;
-                                Token::new(TokenType::Macro(&self.input[start..current]), start_line, start_column)
+                                Token::new(
+                                    TokenType::Macro(&self.input[start..current]),
+                                    start_line,
+                                    start_column,
+                                    self.span(),
+                                )
                             } else {
                                 self.add_multiple_token(macro_directive, (current - start) as u32)
                             }
@@ -338,30 +350,27 @@ impl<'a> Scanner<'a> {
                     let start_line = self.line_number;
                     let start_column = self.column_number;
 
-                    if let Some((_, this_char)) = self.iter.peek() {
-                        match this_char {
-                            '\'' | '\"' => {
-                                let (_, this_char) = self.iter.next().unwrap();
-                                let (current, last_column_break) = self.scan_multiline_string(start, this_char);
-
-                                self.column_number += (current - last_column_break) as u32;
-                                Token::new(TokenType::String(&self.input[start..current]), start_line, start_column)
-                            }
+                    match self.iter.peek() {
+                        Some((_, '\'')) | Some((_, '\"')) => {
+                            let (_, this_char) = self.iter.next().unwrap();
+                            let (current, last_column_break) = self.scan_multiline_string(start, this_char);
+
+                            self.column_number += (current - last_column_break) as u32;
+                            Token::new(
+                                TokenType::String(&self.input[start..current]),
+                                start_line,
+                                start_column,
+                                self.span(),
+                            )
+                        }
 
-                            _ => {
-                                let end_byte = self.next_char_boundary();
-                                self.add_multiple_token(
-                                    TokenType::UnidentifiedInput(&self.input[i..end_byte]),
-                                    (end_byte - i) as u32,
-                                )
-                            }
+                        _ => {
+                            let end_byte = self.next_char_boundary();
+                            self.add_multiple_token(
+                                TokenType::UnidentifiedInput(&self.input[i..end_byte]),
+                                (end_byte - i) as u32,
+                            )
                         }
-                    } else {
-                        let end_byte = self.next_char_boundary();
-                        self.add_multiple_token(
-                            TokenType::UnidentifiedInput(&self.input[i..end_byte]),
-                            (end_byte - i) as u32,
-                        )
                     }
                 }
                 '"' => {
@@ -437,12 +446,12 @@ impl<'a> Scanner<'a> {
                 }
 
                 '.' => match self.iter.peek() {
-                    Some((_, next_char)) if next_char.is_digit(10) => {
+                    Some((_, next_char)) if next_char.is_ascii_digit() => {
                         let start = i;
                         let mut current = start;
 
                         while let Some((_, number_char)) = self.iter.peek() {
-                            if number_char.is_digit(10) {
+                            if number_char.is_ascii_digit() {
                                 self.iter.next();
                                 current = self.next_char_boundary();
                             } else {
@@ -468,7 +477,7 @@ impl<'a> Scanner<'a> {
                                 self.iter.next();
 
                                 while let Some((_, number_char)) = self.iter.peek() {
-                                    if number_char.is_digit(16) {
+                                    if number_char.is_ascii_hexdigit() {
                                         self.iter.next();
                                     } else {
                                         break;
@@ -487,7 +496,7 @@ impl<'a> Scanner<'a> {
 
                     let mut is_fractional = false;
                     while let Some((_, number_char)) = self.iter.peek() {
-                        if number_char.is_digit(10) {
+                        if number_char.is_ascii_digit() {
                             self.iter.next();
                         } else {
                             is_fractional = *number_char == '.';
@@ -501,7 +510,7 @@ impl<'a> Scanner<'a> {
                         self.iter.next();
                         let mut is_end_dot = true;
                         while let Some((_, number_char)) = self.iter.peek() {
-                            if number_char.is_digit(10) {
+                            if number_char.is_ascii_digit() {
                                 is_end_dot = false;
                                 self.iter.next();
                             } else {
@@ -526,7 +535,7 @@ impl<'a> Scanner<'a> {
                     let mut current = self.next_char_boundary();
 
                     while let Some((_, hex_char)) = self.iter.peek() {
-                        if hex_char.is_digit(16) {
+                        if hex_char.is_ascii_hexdigit() {
                             self.iter.next();
                             current = self.next_char_boundary();
                         } else {
@@ -590,6 +599,7 @@ impl<'a> Scanner<'a> {
                             TokenType::MultilineComment(&self.input[start..current]),
                             start_line,
                             start_column,
+                            self.span(),
                         )
                     } else if self.peek_and_check_consume('=') {
                         self.add_multiple_token(TokenType::SlashEquals, 2)
@@ -631,6 +641,7 @@ impl<'a> Scanner<'a> {
                 // Newline
                 '\n' => {
                     let mut tally = 0;
+                    let mut indentation_chars = 0;
                     while let Some((_, c)) = self.iter.peek() {
                         match c {
                             ' ' => {
@@ -643,9 +654,13 @@ impl<'a> Scanner<'a> {
                             }
                             _ => break,
                         };
+                        indentation_chars += 1;
                     }
                     let ret = self.add_multiple_token(TokenType::Newline(tally / 4), tally as u32);
                     self.next_line();
+
+                    // We've already eaten the next line's indentation, so we start after it.
+                    self.column_number = indentation_chars;
                     ret
                 }
 
@@ -674,11 +689,16 @@ impl<'a> Scanner<'a> {
     }
 
     fn add_multiple_token(&mut self, token_type: TokenType<'a>, size: u32) -> Token<'a> {
-        let ret = Token::new(token_type, self.line_number, self.column_number);
+        let ret = Token::new(token_type, self.line_number, self.column_number, self.span());
         self.column_number += size;
         ret
     }
 
+    /// Every token is built once we've consumed all of it, so it runs up to the next character.
+    fn span(&mut self) -> Range<u32> {
+        self.token_start as u32..self.next_char_boundary() as u32
+    }
+
     fn peek_and_check_consume(&mut self, char_to_check: char) -> bool {
         if let Some((_i, next_char)) = self.iter.peek() {
             let ret = next_char == &char_to_check;
@@ -730,7 +750,7 @@ mod scanner_test {
     use super::*;
 
     #[test]
-    fn lex_symbols<'a>() {
+    fn lex_symbols() {
         let input_string = "(){}[] // grouping stuff
 ! * + - / % & | ^ # ? // binary operators
 = == <> > < >= <= // equality operators
@@ -739,72 +759,72 @@ mod scanner_test {
 += -= *= /= ^= |= &= %= // set operators";
 
         let scanner = Scanner::new(input_string);
-        let vec: Vec<Token<'a>> = scanner.collect();
+        let vec: Vec<Token> = scanner.collect();
         assert_eq!(
             vec,
             vec![
                 // line 0
-                Token::new(TokenType::LeftParen, 0, 0),
-                Token::new(TokenType::RightParen, 0, 1),
-                Token::new(TokenType::LeftBrace, 0, 2),
-                Token::new(TokenType::RightBrace, 0, 3),
-                Token::new(TokenType::LeftBracket, 0, 4),
-                Token::new(TokenType::RightBracket, 0, 5),
-                Token::new(TokenType::Comment("// grouping stuff"), 0, 7),
-                Token::new(TokenType::Newline(0), 0, 24),
+                Token::new(TokenType::LeftParen, 0, 0, 0..1),
+                Token::new(TokenType::RightParen, 0, 1, 1..2),
+                Token::new(TokenType::LeftBrace, 0, 2, 2..3),
+                Token::new(TokenType::RightBrace, 0, 3, 3..4),
+                Token::new(TokenType::LeftBracket, 0, 4, 4..5),
+                Token::new(TokenType::RightBracket, 0, 5, 5..6),
+                Token::new(TokenType::Comment("// grouping stuff"), 0, 7, 7..24),
+                Token::new(TokenType::Newline(0), 0, 24, 24..25),
                 // line 1
-                Token::new(TokenType::Bang, 1, 0),
-                Token::new(TokenType::Star, 1, 2),
-                Token::new(TokenType::Plus, 1, 4),
-                Token::new(TokenType::Minus, 1, 6),
-                Token::new(TokenType::Slash, 1, 8),
-                Token::new(TokenType::Mod, 1, 10),
-                Token::new(TokenType::BitAnd, 1, 12),
-                Token::new(TokenType::BitOr, 1, 14),
-                Token::new(TokenType::BitXor, 1, 16),
-                Token::new(TokenType::Hashtag, 1, 18),
-                Token::new(TokenType::Hook, 1, 20),
-                Token::new(TokenType::Comment("// binary operators"), 1, 22),
-                Token::new(TokenType::Newline(0), 1, 41),
+                Token::new(TokenType::Bang, 1, 0, 25..26),
+                Token::new(TokenType::Star, 1, 2, 27..28),
+                Token::new(TokenType::Plus, 1, 4, 29..30),
+                Token::new(TokenType::Minus, 1, 6, 31..32),
+                Token::new(TokenType::Slash, 1, 8, 33..34),
+                Token::new(TokenType::Mod, 1, 10, 35..36),
+                Token::new(TokenType::BitAnd, 1, 12, 37..38),
+                Token::new(TokenType::BitOr, 1, 14, 39..40),
+                Token::new(TokenType::BitXor, 1, 16, 41..42),
+                Token::new(TokenType::Hashtag, 1, 18, 43..44),
+                Token::new(TokenType::Hook, 1, 20, 45..46),
+                Token::new(TokenType::Comment("// binary operators"), 1, 22, 47..66),
+                Token::new(TokenType::Newline(0), 1, 41, 66..67),
                 // line 2
-                Token::new(TokenType::Equal, 2, 0),
-                Token::new(TokenType::EqualEqual, 2, 2),
-                Token::new(TokenType::LessThanGreaterThan, 2, 5),
-                Token::new(TokenType::Greater, 2, 8),
-                Token::new(TokenType::Less, 2, 10),
-                Token::new(TokenType::GreaterEqual, 2, 12),
-                Token::new(TokenType::LessEqual, 2, 15),
-                Token::new(TokenType::Comment("// equality operators"), 2, 18),
-                Token::new(TokenType::Newline(0), 2, 39),
+                Token::new(TokenType::Equal, 2, 0, 67..68),
+                Token::new(TokenType::EqualEqual, 2, 2, 69..71),
+                Token::new(TokenType::LessThanGreaterThan, 2, 5, 72..74),
+                Token::new(TokenType::Greater, 2, 8, 75..76),
+                Token::new(TokenType::Less, 2, 10, 77..78),
+                Token::new(TokenType::GreaterEqual, 2, 12, 79..81),
+                Token::new(TokenType::LessEqual, 2, 15, 82..84),
+                Token::new(TokenType::Comment("// equality operators"), 2, 18, 85..106),
+                Token::new(TokenType::Newline(0), 2, 39, 106..107),
                 // line 3
-                Token::new(TokenType::Dot, 3, 0),
-                Token::new(TokenType::Colon, 3, 1),
-                Token::new(TokenType::Semicolon, 3, 2),
-                Token::new(TokenType::Comma, 3, 3),
-                Token::new(TokenType::Comment("// dots and commas"), 3, 5),
-                Token::new(TokenType::Newline(0), 3, 23),
+                Token::new(TokenType::Dot, 3, 0, 107..108),
+                Token::new(TokenType::Colon, 3, 1, 108..109),
+                Token::new(TokenType::Semicolon, 3, 2, 109..110),
+                Token::new(TokenType::Comma, 3, 3, 110..111),
+                Token::new(TokenType::Comment("// dots and commas"), 3, 5, 112..130),
+                Token::new(TokenType::Newline(0), 3, 23, 130..131),
                 // line 4
-                Token::new(TokenType::LogicalAnd, 4, 0),
-                Token::new(TokenType::LogicalOr, 4, 3),
-                Token::new(TokenType::LogicalXor, 4, 6),
-                Token::new(TokenType::Comment("// logical operators"), 4, 9),
-                Token::new(TokenType::Newline(0), 4, 29),
+                Token::new(TokenType::LogicalAnd, 4, 0, 131..133),
+                Token::new(TokenType::LogicalOr, 4, 3, 134..136),
+                Token::new(TokenType::LogicalXor, 4, 6, 137..139),
+                Token::new(TokenType::Comment("// logical operators"), 4, 9, 140..160),
+                Token::new(TokenType::Newline(0), 4, 29, 160..161),
                 // line 5
-                Token::new(TokenType::PlusEquals, 5, 0),
-                Token::new(TokenType::MinusEquals, 5, 3),
-                Token::new(TokenType::StarEquals, 5, 6),
-                Token::new(TokenType::SlashEquals, 5, 9),
-                Token::new(TokenType::BitXorEquals, 5, 12),
-                Token::new(TokenType::BitOrEquals, 5, 15),
-                Token::new(TokenType::BitAndEquals, 5, 18),
-                Token::new(TokenType::ModEquals, 5, 21),
-                Token::new(TokenType::Comment("// set operators"), 5, 24),
+                Token::new(TokenType::PlusEquals, 5, 0, 161..163),
+                Token::new(TokenType::MinusEquals, 5, 3, 164..166),
+                Token::new(TokenType::StarEquals, 5, 6, 167..169),
+                Token::new(TokenType::SlashEquals, 5, 9, 170..172),
+                Token::new(TokenType::BitXorEquals, 5, 12, 173..175),
+                Token::new(TokenType::BitOrEquals, 5, 15, 176..178),
+                Token::new(TokenType::BitAndEquals, 5, 18, 179..181),
+                Token::new(TokenType::ModEquals, 5, 21, 182..184),
+                Token::new(TokenType::Comment("// set operators"), 5, 24, 185..201),
             ]
         );
     }
 
     #[test]
-    fn lex_strings<'a>() {
+    fn lex_strings() {
         let input_string = "\"This is a good string.\"
 \"This is a bad string.
 \"\"
@@ -812,29 +832,30 @@ mod scanner_test {
 @\"This is a
 multi-linestring. The demon's plaything!\"";
         let scanner = Scanner::new(input_string);
-        let vec: Vec<Token<'a>> = scanner.collect();
+        let vec: Vec<Token> = scanner.collect();
         assert_eq!(
             &vec,
             &vec![
-                Token::new(TokenType::String("\"This is a good string.\""), 0, 0),
-                Token::new(TokenType::Newline(0), 0, 24),
-                Token::new(TokenType::String("\"This is a bad string."), 1, 0),
-                Token::new(TokenType::Newline(0), 1, 22),
-                Token::new(TokenType::String("\"\""), 2, 0),
-                Token::new(TokenType::Newline(0), 2, 2),
-                Token::new(TokenType::String("\"This is another good string!\""), 3, 0),
-                Token::new(TokenType::Newline(0), 3, 30),
+                Token::new(TokenType::String("\"This is a good string.\""), 0, 0, 0..24),
+                Token::new(TokenType::Newline(0), 0, 24, 24..25),
+                Token::new(TokenType::String("\"This is a bad string."), 1, 0, 25..47),
+                Token::new(TokenType::Newline(0), 1, 22, 47..48),
+                Token::new(TokenType::String("\"\""), 2, 0, 48..50),
+                Token::new(TokenType::Newline(0), 2, 2, 50..51),
+                Token::new(TokenType::String("\"This is another good string!\""), 3, 0, 51..81),
+                Token::new(TokenType::Newline(0), 3, 30, 81..82),
                 Token::new(
                     TokenType::String("@\"This is a\nmulti-linestring. The demon's plaything!\""),
                     4,
-                    0
+                    0,
+                    82..135,
                 ),
             ]
         );
     }
 
     #[test]
-    fn lex_numbers<'a>() {
+    fn lex_numbers() {
         let input_string = "314159
 3.14159
 314159.
@@ -845,31 +866,31 @@ multi-linestring. The demon's plaything!\"";
 .3";
 
         let scanner = Scanner::new(input_string);
-        let vec: Vec<Token<'a>> = scanner.collect();
+        let vec: Vec<Token> = scanner.collect();
         assert_eq!(
             &vec,
             &vec![
-                Token::new(TokenType::Number("314159"), 0, 0),
-                Token::new(TokenType::Newline(0), 0, 6),
-                Token::new(TokenType::Number("3.14159"), 1, 0),
-                Token::new(TokenType::Newline(0), 1, 7),
-                Token::new(TokenType::NumberEndDot("314159."), 2, 0),
-                Token::new(TokenType::Newline(0), 2, 7),
-                Token::new(TokenType::NumberStartDot(".314159"), 3, 0),
-                Token::new(TokenType::Newline(0), 3, 7),
-                Token::new(TokenType::Number("4"), 4, 0),
-                Token::new(TokenType::Newline(0), 4, 1),
-                Token::new(TokenType::Number("9"), 5, 0),
-                Token::new(TokenType::Newline(0), 5, 1),
-                Token::new(TokenType::Number("0"), 6, 0),
-                Token::new(TokenType::Newline(0), 6, 1),
-                Token::new(TokenType::NumberStartDot(".3"), 7, 0),
+                Token::new(TokenType::Number("314159"), 0, 0, 0..6),
+                Token::new(TokenType::Newline(0), 0, 6, 6..7),
+                Token::new(TokenType::Number("3.14159"), 1, 0, 7..14),
+                Token::new(TokenType::Newline(0), 1, 7, 14..15),
+                Token::new(TokenType::NumberEndDot("314159."), 2, 0, 15..22),
+                Token::new(TokenType::Newline(0), 2, 7, 22..23),
+                Token::new(TokenType::NumberStartDot(".314159"), 3, 0, 23..30),
+                Token::new(TokenType::Newline(0), 3, 7, 30..31),
+                Token::new(TokenType::Number("4"), 4, 0, 31..32),
+                Token::new(TokenType::Newline(0), 4, 1, 32..33),
+                Token::new(TokenType::Number("9"), 5, 0, 33..34),
+                Token::new(TokenType::Newline(0), 5, 1, 34..35),
+                Token::new(TokenType::Number("0"), 6, 0, 35..36),
+                Token::new(TokenType::Newline(0), 6, 1, 36..37),
+                Token::new(TokenType::NumberStartDot(".3"), 7, 0, 37..39),
             ]
         );
     }
 
     #[test]
-    fn lex_hex<'a>() {
+    fn lex_hex() {
         let input_string = "0123456789
 0x01234567
 0x0A1B2C3D4E5F6
@@ -881,33 +902,33 @@ $ABCDEF
 $";
 
         let scanner = Scanner::new(input_string);
-        let vec: Vec<Token<'a>> = scanner.collect();
+        let vec: Vec<Token> = scanner.collect();
         assert_eq!(
             &vec,
             &vec![
-                Token::new(TokenType::Number("0123456789"), 0, 0),
-                Token::new(TokenType::Newline(0), 0, 10),
-                Token::new(TokenType::Number("0x01234567"), 1, 0),
-                Token::new(TokenType::Newline(0), 1, 10),
-                Token::new(TokenType::Number("0x0A1B2C3D4E5F6"), 2, 0),
-                Token::new(TokenType::Newline(0), 2, 15),
-                Token::new(TokenType::Number("0xABCDEF"), 3, 0),
-                Token::new(TokenType::Newline(0), 3, 8),
-                Token::new(TokenType::Number("0x"), 4, 0),
-                Token::new(TokenType::Newline(0), 4, 2),
-                Token::new(TokenType::Number("$012345"), 5, 0),
-                Token::new(TokenType::Newline(0), 5, 7),
-                Token::new(TokenType::Number("$0A1B2C3D4E5F6"), 6, 0),
-                Token::new(TokenType::Newline(0), 6, 14),
-                Token::new(TokenType::Number("$ABCDEF"), 7, 0),
-                Token::new(TokenType::Newline(0), 7, 7),
-                Token::new(TokenType::Number("$"), 8, 0),
+                Token::new(TokenType::Number("0123456789"), 0, 0, 0..10),
+                Token::new(TokenType::Newline(0), 0, 10, 10..11),
+                Token::new(TokenType::Number("0x01234567"), 1, 0, 11..21),
+                Token::new(TokenType::Newline(0), 1, 10, 21..22),
+                Token::new(TokenType::Number("0x0A1B2C3D4E5F6"), 2, 0, 22..37),
+                Token::new(TokenType::Newline(0), 2, 15, 37..38),
+                Token::new(TokenType::Number("0xABCDEF"), 3, 0, 38..46),
+                Token::new(TokenType::Newline(0), 3, 8, 46..47),
+                Token::new(TokenType::Number("0x"), 4, 0, 47..49),
+                Token::new(TokenType::Newline(0), 4, 2, 49..50),
+                Token::new(TokenType::Number("$012345"), 5, 0, 50..57),
+                Token::new(TokenType::Newline(0), 5, 7, 57..58),
+                Token::new(TokenType::Number("$0A1B2C3D4E5F6"), 6, 0, 58..72),
+                Token::new(TokenType::Newline(0), 6, 14, 72..73),
+                Token::new(TokenType::Number("$ABCDEF"), 7, 0, 73..80),
+                Token::new(TokenType::Newline(0), 7, 7, 80..81),
+                Token::new(TokenType::Number("$"), 8, 0, 81..82),
             ]
         );
     }
 
     #[test]
-    fn lex_basic_identifiers<'a>() {
+    fn lex_basic_identifiers() {
         let input_string = "a
 Z
 AbCdE
@@ -917,95 +938,95 @@ test_123
 testCase";
 
         let scanner = Scanner::new(input_string);
-        let vec: Vec<Token<'a>> = scanner.collect();
+        let vec: Vec<Token> = scanner.collect();
         assert_eq!(
             &vec,
             &vec![
-                Token::new(TokenType::Identifier("a"), 0, 0),
-                Token::new(TokenType::Newline(0), 0, 1),
-                Token::new(TokenType::Identifier("Z"), 1, 0),
-                Token::new(TokenType::Newline(0), 1, 1),
-                Token::new(TokenType::Identifier("AbCdE"), 2, 0),
-                Token::new(TokenType::Newline(0), 2, 5),
-                Token::new(TokenType::Identifier("_test"), 3, 0),
-                Token::new(TokenType::Newline(0), 3, 5),
-                Token::new(TokenType::Identifier("_test123"), 4, 0),
-                Token::new(TokenType::Newline(0), 4, 8),
-                Token::new(TokenType::Identifier("test_123"), 5, 0),
-                Token::new(TokenType::Newline(0), 5, 8),
-                Token::new(TokenType::Identifier("testCase"), 6, 0),
+                Token::new(TokenType::Identifier("a"), 0, 0, 0..1),
+                Token::new(TokenType::Newline(0), 0, 1, 1..2),
+                Token::new(TokenType::Identifier("Z"), 1, 0, 2..3),
+                Token::new(TokenType::Newline(0), 1, 1, 3..4),
+                Token::new(TokenType::Identifier("AbCdE"), 2, 0, 4..9),
+                Token::new(TokenType::Newline(0), 2, 5, 9..10),
+                Token::new(TokenType::Identifier("_test"), 3, 0, 10..15),
+                Token::new(TokenType::Newline(0), 3, 5, 15..16),
+                Token::new(TokenType::Identifier("_test123"), 4, 0, 16..24),
+                Token::new(TokenType::Newline(0), 4, 8, 24..25),
+                Token::new(TokenType::Identifier("test_123"), 5, 0, 25..33),
+                Token::new(TokenType::Newline(0), 5, 8, 33..34),
+                Token::new(TokenType::Identifier("testCase"), 6, 0, 34..42),
             ]
         )
     }
 
     #[test]
-    fn lex_reserved_keywords<'a>() {
+    fn lex_reserved_keywords() {
         let input_string = "var and or if else return for repeat while do until switch case default div break enum";
 
         let scanner = Scanner::new(input_string);
-        let vec: Vec<Token<'a>> = scanner.collect();
+        let vec: Vec<Token> = scanner.collect();
         assert_eq!(
             &vec,
             &vec![
-                Token::new(TokenType::Var, 0, 0),
-                Token::new(TokenType::AndAlias, 0, 4),
-                Token::new(TokenType::OrAlias, 0, 8),
-                Token::new(TokenType::If, 0, 11),
-                Token::new(TokenType::Else, 0, 14),
-                Token::new(TokenType::Return, 0, 19),
-                Token::new(TokenType::For, 0, 26),
-                Token::new(TokenType::Repeat, 0, 30),
-                Token::new(TokenType::While, 0, 37),
-                Token::new(TokenType::Do, 0, 43),
-                Token::new(TokenType::Until, 0, 46),
-                Token::new(TokenType::Switch, 0, 52),
-                Token::new(TokenType::Case, 0, 59),
-                Token::new(TokenType::DefaultCase, 0, 64),
-                Token::new(TokenType::Div, 0, 72),
-                Token::new(TokenType::Break, 0, 76),
-                Token::new(TokenType::Enum, 0, 82),
+                Token::new(TokenType::Var, 0, 0, 0..3),
+                Token::new(TokenType::AndAlias, 0, 4, 4..7),
+                Token::new(TokenType::OrAlias, 0, 8, 8..10),
+                Token::new(TokenType::If, 0, 11, 11..13),
+                Token::new(TokenType::Else, 0, 14, 14..18),
+                Token::new(TokenType::Return, 0, 19, 19..25),
+                Token::new(TokenType::For, 0, 26, 26..29),
+                Token::new(TokenType::Repeat, 0, 30, 30..36),
+                Token::new(TokenType::While, 0, 37, 37..42),
+                Token::new(TokenType::Do, 0, 43, 43..45),
+                Token::new(TokenType::Until, 0, 46, 46..51),
+                Token::new(TokenType::Switch, 0, 52, 52..58),
+                Token::new(TokenType::Case, 0, 59, 59..63),
+                Token::new(TokenType::DefaultCase, 0, 64, 64..71),
+                Token::new(TokenType::Div, 0, 72, 72..75),
+                Token::new(TokenType::Break, 0, 76, 76..81),
+                Token::new(TokenType::Enum, 0, 82, 82..86),
             ]
         )
     }
 
     #[test]
-    fn lex_alias_words<'a>() {
+    fn lex_alias_words() {
         let input_string = "and not or mod";
 
         let scanner = Scanner::new(input_string);
-        let vec: Vec<Token<'a>> = scanner.collect();
+        let vec: Vec<Token> = scanner.collect();
         assert_eq!(
             &vec,
             &vec![
-                Token::new(TokenType::AndAlias, 0, 0),
-                Token::new(TokenType::NotAlias, 0, 4),
-                Token::new(TokenType::OrAlias, 0, 8),
-                Token::new(TokenType::ModAlias, 0, 11),
+                Token::new(TokenType::AndAlias, 0, 0, 0..3),
+                Token::new(TokenType::NotAlias, 0, 4, 4..7),
+                Token::new(TokenType::OrAlias, 0, 8, 8..10),
+                Token::new(TokenType::ModAlias, 0, 11, 11..14),
             ]
         )
     }
 
     #[test]
-    fn lex_indexers<'a>() {
+    fn lex_indexers() {
         let input_string = "[ [? [# [| [@ ]";
 
         let scanner = Scanner::new(input_string);
-        let vec: Vec<Token<'a>> = scanner.collect();
+        let vec: Vec<Token> = scanner.collect();
         assert_eq!(
             &vec,
             &vec![
-                Token::new(TokenType::LeftBracket, 0, 0),
-                Token::new(TokenType::MapIndexer, 0, 2),
-                Token::new(TokenType::GridIndexer, 0, 5),
-                Token::new(TokenType::ListIndexer, 0, 8),
-                Token::new(TokenType::ArrayIndexer, 0, 11),
-                Token::new(TokenType::RightBracket, 0, 14),
+                Token::new(TokenType::LeftBracket, 0, 0, 0..1),
+                Token::new(TokenType::MapIndexer, 0, 2, 2..4),
+                Token::new(TokenType::GridIndexer, 0, 5, 5..7),
+                Token::new(TokenType::ListIndexer, 0, 8, 8..10),
+                Token::new(TokenType::ArrayIndexer, 0, 11, 11..13),
+                Token::new(TokenType::RightBracket, 0, 14, 14..15),
             ]
         )
     }
 
     #[test]
-    fn lex_compiler_directives<'a>() {
+    fn lex_compiler_directives() {
         let input_string = "#region Region Name Long
 #macro macroName 0
 #endregion
@@ -1013,22 +1034,22 @@ testCase";
 is bad";
 
         let scanner = Scanner::new(input_string);
-        let vec: Vec<Token<'a>> = scanner.collect();
+        let vec: Vec<Token> = scanner.collect();
         assert_eq!(
             &vec,
             &vec![
-                Token::new(TokenType::RegionBegin("#region Region Name Long"), 0, 0),
-                Token::new(TokenType::Newline(0), 0, 24),
-                Token::new(TokenType::Macro("#macro macroName 0"), 1, 0),
-                Token::new(TokenType::Newline(0), 1, 18),
-                Token::new(TokenType::RegionEnd("#endregion"), 2, 0),
-                Token::new(TokenType::Newline(0), 2, 10),
-                Token::new(TokenType::Macro("#macro doing this \\\nis bad"), 3, 0),
+                Token::new(TokenType::RegionBegin("#region Region Name Long"), 0, 0, 0..24),
+                Token::new(TokenType::Newline(0), 0, 24, 24..25),
+                Token::new(TokenType::Macro("#macro macroName 0"), 1, 0, 25..43),
+                Token::new(TokenType::Newline(0), 1, 18, 43..44),
+                Token::new(TokenType::RegionEnd("#endregion"), 2, 0, 44..54),
+                Token::new(TokenType::Newline(0), 2, 10, 54..55),
+                Token::new(TokenType::Macro("#macro doing this \\\nis bad"), 3, 0, 55..81),
             ]
         )
     }
     #[test]
-    fn lex_comments<'a>() {
+    fn lex_comments() {
         let input_string = "// normal comment
 var x = a; // end comment
 /* one liner */
@@ -1036,26 +1057,26 @@ var x = a; // end comment
 liner comment
 */";
         let scanner = Scanner::new(input_string);
-        let vec: Vec<Token<'a>> = scanner.collect();
+        let vec: Vec<Token> = scanner.collect();
         assert_eq!(
             &vec,
             &vec![
                 // line 0
-                Token::new(TokenType::Comment("// normal comment"), 0, 0),
-                Token::new(TokenType::Newline(0), 0, 17),
+                Token::new(TokenType::Comment("// normal comment"), 0, 0, 0..17),
+                Token::new(TokenType::Newline(0), 0, 17, 17..18),
                 // line 1
-                Token::new(TokenType::Var, 1, 0),
-                Token::new(TokenType::Identifier("x"), 1, 4),
-                Token::new(TokenType::Equal, 1, 6),
-                Token::new(TokenType::Identifier("a"), 1, 8),
-                Token::new(TokenType::Semicolon, 1, 9),
-                Token::new(TokenType::Comment("// end comment"), 1, 11),
-                Token::new(TokenType::Newline(0), 1, 25),
+                Token::new(TokenType::Var, 1, 0, 18..21),
+                Token::new(TokenType::Identifier("x"), 1, 4, 22..23),
+                Token::new(TokenType::Equal, 1, 6, 24..25),
+                Token::new(TokenType::Identifier("a"), 1, 8, 26..27),
+                Token::new(TokenType::Semicolon, 1, 9, 27..28),
+                Token::new(TokenType::Comment("// end comment"), 1, 11, 29..43),
+                Token::new(TokenType::Newline(0), 1, 25, 43..44),
                 // line 2
-                Token::new(TokenType::MultilineComment("/* one liner */"), 2, 0),
-                Token::new(TokenType::Newline(0), 2, 15),
+                Token::new(TokenType::MultilineComment("/* one liner */"), 2, 0, 44..59),
+                Token::new(TokenType::Newline(0), 2, 15, 59..60),
                 // line 3
-                Token::new(TokenType::MultilineComment("/* multi\nliner comment\n*/"), 3, 0),
+                Token::new(TokenType::MultilineComment("/* multi\nliner comment\n*/"), 3, 0, 60..85),
             ]
         )
     }
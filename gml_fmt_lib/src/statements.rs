@@ -1,17 +1,23 @@
 use super::expressions::*;
 use super::lex_token::Token;
+use serde::Serialize;
+use std::ops::Range;
 
 pub type StmtBox<'a> = Box<StatementWrapper<'a>>;
-#[derive(Debug)]
+/// A comma separated list, such as call arguments or enum members, one item per `DelimitedLine`.
+#[derive(Debug, Serialize)]
 pub struct DelimitedLines<'a, T> {
     pub lines: Vec<DelimitedLine<'a, T>>,
     pub has_end_delimiter: bool,
 }
 
-#[derive(Debug)]
+/// A statement, and whether it was written with a semicolon.
+#[derive(Debug, Serialize)]
 pub struct StatementWrapper<'a> {
     pub statement: Statement<'a>,
     pub has_semicolon: bool,
+    /// The bytes of the source the statement was written in, not counting any comments trailing it.
+    pub span: Range<u32>,
 }
 
 impl<'a> StatementWrapper<'a> {
@@ -19,19 +25,17 @@ impl<'a> StatementWrapper<'a> {
         Box::new(StatementWrapper {
             statement,
             has_semicolon,
+            span: 0..0,
         })
     }
 
     pub fn hold_expr(&self) -> bool {
-        if let Statement::ExpresssionStatement { .. } = &self.statement {
-            true
-        } else {
-            false
-        }
+        matches!(self.statement, Statement::ExpressionStatement { .. })
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
+#[non_exhaustive]
 pub enum Statement<'a> {
     VariableDeclList {
         starting_var_type: Token<'a>,
@@ -81,7 +85,7 @@ pub enum Statement<'a> {
         comments_after_lbrace: CommentsAndNewlines<'a>,
         cases: Vec<Case<'a>>,
     },
-    ExpresssionStatement {
+    ExpressionStatement {
         expression: ExprBox<'a>,
     },
     Block {
@@ -102,6 +106,10 @@ pub enum Statement<'a> {
     RegionBegin(Token<'a>),
     RegionEnd(Token<'a>),
     Macro(Token<'a>),
+    /// Source we couldn't parse, kept exactly as it was written.
+    Unknown {
+        source: &'a str,
+    },
     Define {
         comments_after_control_word: CommentsAndNewlines<'a>,
         script_name: ExprBox<'a>,
@@ -109,7 +117,7 @@ pub enum Statement<'a> {
     },
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
 pub struct Case<'a> {
     pub control_word: CaseType<'a>,
     pub comments_after_control_word: CommentsAndNewlines<'a>,
@@ -117,20 +125,20 @@ pub struct Case<'a> {
     pub statements: Vec<StmtBox<'a>>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
 pub enum CaseType<'a> {
     Case(ExprBox<'a>),
     Default,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
 pub struct VariableDecl<'a> {
     pub var_expr: ExprBox<'a>,
     pub say_var: Option<Token<'a>>,
     pub say_var_comments: Option<CommentsAndNewlines<'a>>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
 pub struct DelimitedLine<'a, T> {
     pub expr: T,
     pub trailing_comment: CommentsAndNewlines<'a>,
@@ -0,0 +1,55 @@
+use super::diagnostic::Diagnostic;
+use super::lex_token::{Token, TokenType};
+use super::line_index::LineIndex;
+use super::printer::Printer;
+use super::scanner::Scanner;
+
+/// Checks that formatting only moved whitespace around, by lexing `input` and
+/// `output` again and comparing what's left once newlines are set aside. The
+/// printer is allowed to add and remove semicolons, so we set those aside too,
+/// and to write `.5` and `5.` out in full as `0.5` and `5.0`.
+///
+/// If the two differ, we point at the first token which changed.
+pub fn verify_tokens(input: &str, output: &str) -> Result<(), Diagnostic> {
+    let mut before = Scanner::new(input).filter(significant);
+    let mut after = Scanner::new(output).filter(significant);
+
+    loop {
+        let (message, token) = match (before.next(), after.next()) {
+            (None, None) => return Ok(()),
+            (Some(old), Some(new)) if same_token(&old.token_type, &new.token_type) => continue,
+
+            (Some(old), Some(new)) => (
+                format!("formatting changed `{}` into `{}`", lexeme(&old), lexeme(&new)),
+                Some(old),
+            ),
+            (Some(old), None) => (format!("formatting removed `{}`", lexeme(&old)), Some(old)),
+            (None, Some(new)) => (format!("formatting added `{}`", lexeme(&new)), None),
+        };
+
+        let lines = LineIndex::new(input);
+        let diagnostic = match token {
+            Some(token) => Diagnostic::error_at(&lines, &token, message),
+            None => Diagnostic::error_at_end(&lines, message),
+        };
+
+        return Err(diagnostic
+            .with_help("this is a bug in gml_fmt, so the file was left untouched. Please report it with this file!"));
+    }
+}
+
+fn significant(token: &Token) -> bool {
+    matches!(token.token_type, TokenType::Newline(_) | TokenType::Semicolon) == false
+}
+
+fn same_token(old: &TokenType, new: &TokenType) -> bool {
+    match (old, new) {
+        (TokenType::NumberStartDot(old), TokenType::Number(new)) => new.strip_prefix('0') == Some(*old),
+        (TokenType::NumberEndDot(old), TokenType::Number(new)) => new.strip_suffix('0') == Some(*old),
+        _ => old == new,
+    }
+}
+
+fn lexeme<'a>(token: &'a Token<'a>) -> String {
+    Printer::get_token_name(&token.token_type).escape_debug().to_string()
+}
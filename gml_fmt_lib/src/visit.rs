@@ -0,0 +1,447 @@
+//! Walking the syntax tree from `parse`.
+//!
+//! Implement `Visitor` (or `VisitorMut`, to change the tree) and override only the
+//! `visit_*` methods you care about. Each default method calls the matching `walk_*`
+//! function, which visits every child in the order it was written, so an override
+//! which still wants to reach the children calls `walk_*` itself.
+//!
+//! ```
+//! use gml_fmt_lib::ast::{Expr, ExprBoxInterior};
+//! use gml_fmt_lib::visit::{walk_expr, Visitor};
+//!
+//! #[derive(Default)]
+//! struct Calls(usize);
+//!
+//! impl<'a> Visitor<'a> for Calls {
+//!     fn visit_expr(&mut self, expr: &ExprBoxInterior<'a>) {
+//!         if let Expr::Call { .. } = expr.expr {
+//!             self.0 += 1;
+//!         }
+//!         walk_expr(self, expr);
+//!     }
+//! }
+//!
+//! let ast = gml_fmt_lib::parse("a(b(), c);").unwrap();
+//! let mut calls = Calls::default();
+//! calls.visit_ast(&ast);
+//! assert_eq!(calls.0, 2);
+//! ```
+
+use super::ast::*;
+
+// `Visitor` and `VisitorMut` only differ in how they borrow the tree, so both are
+// written once here, with `$mutability` left empty for one and `mut` for the other.
+macro_rules! visitor {
+    (
+        $(#[$trait_doc:meta])*
+        trait $visitor:ident;
+        $(#[$visitable_doc:meta])*
+        trait $visitable:ident;
+        ($($mutability:tt)?)
+        $visit_ast:ident,
+        $visit_statement:ident => $walk_statement:ident,
+        $visit_expr:ident => $walk_expr:ident,
+        $visit_case:ident => $walk_case:ident,
+        $visit_variable_decl:ident => $walk_variable_decl:ident,
+        $visit_delimited_line:ident => $walk_delimited_line:ident,
+        $visit_comments:ident => $walk_comments:ident,
+        $visit_token:ident,
+        $accept:ident,
+    ) => {
+        $(#[$trait_doc])*
+        pub trait $visitor<'a> {
+            fn $visit_ast(&mut self, ast: &$($mutability)? Ast<'a>) {
+                for statement in &$($mutability)? ast.statements {
+                    self.$visit_statement(statement);
+                }
+            }
+
+            fn $visit_statement(&mut self, statement: &$($mutability)? StatementWrapper<'a>) {
+                $walk_statement(self, statement);
+            }
+
+            fn $visit_expr(&mut self, expr: &$($mutability)? ExprBoxInterior<'a>) {
+                $walk_expr(self, expr);
+            }
+
+            fn $visit_case(&mut self, case: &$($mutability)? Case<'a>) {
+                $walk_case(self, case);
+            }
+
+            fn $visit_variable_decl(&mut self, variable_decl: &$($mutability)? VariableDecl<'a>) {
+                $walk_variable_decl(self, variable_decl);
+            }
+
+            fn $visit_delimited_line<T: $visitable<'a>>(&mut self, line: &$($mutability)? DelimitedLine<'a, T>) {
+                $walk_delimited_line(self, line);
+            }
+
+            /// Visits a slot of comments and newlines, which is often empty.
+            fn $visit_comments(&mut self, comments: &$($mutability)? CommentsAndNewlines<'a>) {
+                $walk_comments(self, comments);
+            }
+
+            /// Visits every token kept in the tree: keywords, operators, names,
+            /// literals, comments and newlines.
+            fn $visit_token(&mut self, _token: &$($mutability)? Token<'a>) {}
+        }
+
+        $(#[$visitable_doc])*
+        pub trait $visitable<'a> {
+            fn $accept<V: $visitor<'a> + ?Sized>(&$($mutability)? self, visitor: &mut V);
+        }
+
+        impl<'a> $visitable<'a> for ExprBox<'a> {
+            fn $accept<V: $visitor<'a> + ?Sized>(&$($mutability)? self, visitor: &mut V) {
+                visitor.$visit_expr(self);
+            }
+        }
+
+        impl<'a> $visitable<'a> for VariableDecl<'a> {
+            fn $accept<V: $visitor<'a> + ?Sized>(&$($mutability)? self, visitor: &mut V) {
+                visitor.$visit_variable_decl(self);
+            }
+        }
+
+        pub fn $walk_statement<'a, V: $visitor<'a> + ?Sized>(
+            visitor: &mut V,
+            statement: &$($mutability)? StatementWrapper<'a>,
+        ) {
+            match &$($mutability)? statement.statement {
+                Statement::VariableDeclList {
+                    starting_var_type,
+                    comments_after_control_word,
+                    var_decl,
+                } => {
+                    visitor.$visit_token(starting_var_type);
+                    visitor.$visit_comments(comments_after_control_word);
+                    for line in &$($mutability)? var_decl.lines {
+                        visitor.$visit_delimited_line(line);
+                    }
+                }
+                Statement::EnumDeclaration {
+                    comments_after_control_word,
+                    name,
+                    comments_after_lbrace,
+                    members,
+                } => {
+                    visitor.$visit_comments(comments_after_control_word);
+                    visitor.$visit_expr(name);
+                    visitor.$visit_comments(comments_after_lbrace);
+                    for line in &$($mutability)? members.lines {
+                        visitor.$visit_delimited_line(line);
+                    }
+                }
+                Statement::If {
+                    comments_after_control_word,
+                    condition,
+                    then_branch,
+                    comments_between,
+                    else_branch,
+                } => {
+                    visitor.$visit_comments(comments_after_control_word);
+                    visitor.$visit_expr(condition);
+                    visitor.$visit_statement(then_branch);
+                    visitor.$visit_comments(comments_between);
+                    if let Some(else_branch) = else_branch {
+                        visitor.$visit_statement(else_branch);
+                    }
+                }
+                Statement::WhileWithRepeat {
+                    comments_after_control_word,
+                    token,
+                    condition,
+                    body,
+                } => {
+                    visitor.$visit_token(token);
+                    visitor.$visit_comments(comments_after_control_word);
+                    visitor.$visit_expr(condition);
+                    visitor.$visit_statement(body);
+                }
+                Statement::DoUntil {
+                    comments_after_control_word,
+                    body,
+                    comments_between,
+                    condition,
+                } => {
+                    visitor.$visit_comments(comments_after_control_word);
+                    visitor.$visit_statement(body);
+                    visitor.$visit_comments(comments_between);
+                    visitor.$visit_expr(condition);
+                }
+                Statement::For {
+                    comments_after_control_word,
+                    comments_after_lparen,
+                    initializer,
+                    comments_after_initializer,
+                    condition,
+                    comments_after_condition,
+                    increment,
+                    comments_after_increment,
+                    comments_after_rparen,
+                    body,
+                } => {
+                    visitor.$visit_comments(comments_after_control_word);
+                    visitor.$visit_comments(comments_after_lparen);
+                    if let Some(initializer) = initializer {
+                        visitor.$visit_statement(initializer);
+                    }
+                    visitor.$visit_comments(comments_after_initializer);
+                    if let Some(condition) = condition {
+                        visitor.$visit_expr(condition);
+                    }
+                    visitor.$visit_comments(comments_after_condition);
+                    if let Some(increment) = increment {
+                        visitor.$visit_expr(increment);
+                    }
+                    visitor.$visit_comments(comments_after_increment);
+                    visitor.$visit_comments(comments_after_rparen);
+                    visitor.$visit_statement(body);
+                }
+                Statement::Switch {
+                    comments_after_control_word,
+                    condition,
+                    comments_after_lbrace,
+                    cases,
+                } => {
+                    visitor.$visit_comments(comments_after_control_word);
+                    visitor.$visit_expr(condition);
+                    visitor.$visit_comments(comments_after_lbrace);
+                    for case in cases {
+                        visitor.$visit_case(case);
+                    }
+                }
+                Statement::ExpressionStatement { expression } => visitor.$visit_expr(expression),
+                Statement::Block {
+                    comments_after_lbrace,
+                    statements,
+                } => {
+                    visitor.$visit_comments(comments_after_lbrace);
+                    for statement in statements {
+                        visitor.$visit_statement(statement);
+                    }
+                }
+                Statement::Return { expression } => {
+                    if let Some(expression) = expression {
+                        visitor.$visit_expr(expression);
+                    }
+                }
+                Statement::Break | Statement::Exit | Statement::Unknown { .. } => {}
+                Statement::Comment { comment } => visitor.$visit_token(comment),
+                Statement::MultilineComment { multiline_comment } => visitor.$visit_token(multiline_comment),
+                Statement::RegionBegin(token) | Statement::RegionEnd(token) | Statement::Macro(token) => {
+                    visitor.$visit_token(token);
+                }
+                Statement::Define {
+                    comments_after_control_word,
+                    script_name,
+                    body,
+                } => {
+                    visitor.$visit_comments(comments_after_control_word);
+                    visitor.$visit_expr(script_name);
+                    for statement in body {
+                        visitor.$visit_statement(statement);
+                    }
+                }
+            }
+        }
+
+        pub fn $walk_expr<'a, V: $visitor<'a> + ?Sized>(visitor: &mut V, expr: &$($mutability)? ExprBoxInterior<'a>) {
+            match &$($mutability)? expr.expr {
+                Expr::Call {
+                    procedure_name,
+                    comments_and_newlines_after_lparen,
+                    arguments,
+                } => {
+                    visitor.$visit_expr(procedure_name);
+                    visitor.$visit_comments(comments_and_newlines_after_lparen);
+                    for line in &$($mutability)? arguments.lines {
+                        visitor.$visit_delimited_line(line);
+                    }
+                }
+                Expr::Binary {
+                    left,
+                    operator,
+                    comments_and_newlines_between_op_and_r,
+                    right,
+                }
+                | Expr::Assign {
+                    left,
+                    operator,
+                    comments_and_newlines_between_op_and_r,
+                    right,
+                } => {
+                    visitor.$visit_expr(left);
+                    visitor.$visit_token(operator);
+                    visitor.$visit_comments(comments_and_newlines_between_op_and_r);
+                    visitor.$visit_expr(right);
+                }
+                Expr::Grouping {
+                    comments_and_newlines_after_lparen,
+                    expressions,
+                    comments_and_newlines_after_rparen,
+                } => {
+                    visitor.$visit_comments(comments_and_newlines_after_lparen);
+                    for expression in expressions {
+                        visitor.$visit_expr(expression);
+                    }
+                    visitor.$visit_comments(comments_and_newlines_after_rparen);
+                }
+                Expr::ArrayLiteral {
+                    comments_and_newlines_after_lbracket,
+                    arguments,
+                } => {
+                    visitor.$visit_comments(comments_and_newlines_after_lbracket);
+                    for line in &$($mutability)? arguments.lines {
+                        visitor.$visit_delimited_line(line);
+                    }
+                }
+                Expr::Literal { literal_token, comments }
+                | Expr::NumberStartDot { literal_token, comments }
+                | Expr::NumberEndDot { literal_token, comments } => {
+                    visitor.$visit_token(literal_token);
+                    visitor.$visit_comments(comments);
+                }
+                Expr::Unary {
+                    operator,
+                    comments_and_newlines_between,
+                    right,
+                } => {
+                    visitor.$visit_token(operator);
+                    visitor.$visit_comments(comments_and_newlines_between);
+                    visitor.$visit_expr(right);
+                }
+                Expr::Postfix {
+                    operator,
+                    comments_and_newlines_between,
+                    expr,
+                } => {
+                    visitor.$visit_expr(expr);
+                    visitor.$visit_token(operator);
+                    visitor.$visit_comments(comments_and_newlines_between);
+                }
+                Expr::Identifier { name, comments } => {
+                    visitor.$visit_token(name);
+                    visitor.$visit_comments(comments);
+                }
+                Expr::DotAccess {
+                    object_name,
+                    comments_between,
+                    instance_variable,
+                } => {
+                    visitor.$visit_expr(object_name);
+                    visitor.$visit_comments(comments_between);
+                    visitor.$visit_expr(instance_variable);
+                }
+                Expr::DataStructureAccess {
+                    ds_name,
+                    access_type,
+                    access_exprs,
+                } => {
+                    visitor.$visit_expr(ds_name);
+                    visitor.$visit_token(access_type);
+                    for (comments, access_expr) in access_exprs {
+                        visitor.$visit_comments(comments);
+                        visitor.$visit_expr(access_expr);
+                    }
+                }
+                Expr::Ternary {
+                    conditional,
+                    comments_and_newlines_after_q,
+                    left,
+                    comments_and_newlines_after_colon,
+                    right,
+                } => {
+                    visitor.$visit_expr(conditional);
+                    visitor.$visit_comments(comments_and_newlines_after_q);
+                    visitor.$visit_expr(left);
+                    visitor.$visit_comments(comments_and_newlines_after_colon);
+                    visitor.$visit_expr(right);
+                }
+                Expr::Newline => {}
+                Expr::Comment { comment } => visitor.$visit_token(comment),
+                Expr::MultilineComment { multiline_comment } => visitor.$visit_token(multiline_comment),
+                Expr::UnidentifiedAsLiteral { literal_token } => visitor.$visit_token(literal_token),
+            }
+
+            visitor.$visit_comments(&$($mutability)? expr.trailing_comments);
+        }
+
+        pub fn $walk_case<'a, V: $visitor<'a> + ?Sized>(visitor: &mut V, case: &$($mutability)? Case<'a>) {
+            if let CaseType::Case(expr) = &$($mutability)? case.control_word {
+                visitor.$visit_expr(expr);
+            }
+            visitor.$visit_comments(&$($mutability)? case.comments_after_control_word);
+            visitor.$visit_comments(&$($mutability)? case.comments_after_colon);
+            for statement in &$($mutability)? case.statements {
+                visitor.$visit_statement(statement);
+            }
+        }
+
+        pub fn $walk_variable_decl<'a, V: $visitor<'a> + ?Sized>(
+            visitor: &mut V,
+            variable_decl: &$($mutability)? VariableDecl<'a>,
+        ) {
+            if let Some(say_var) = &$($mutability)? variable_decl.say_var {
+                visitor.$visit_token(say_var);
+            }
+            if let Some(comments) = &$($mutability)? variable_decl.say_var_comments {
+                visitor.$visit_comments(comments);
+            }
+            visitor.$visit_expr(&$($mutability)? variable_decl.var_expr);
+        }
+
+        pub fn $walk_delimited_line<'a, V: $visitor<'a> + ?Sized, T: $visitable<'a>>(
+            visitor: &mut V,
+            line: &$($mutability)? DelimitedLine<'a, T>,
+        ) {
+            line.expr.$accept(visitor);
+            visitor.$visit_comments(&$($mutability)? line.trailing_comment);
+        }
+
+        pub fn $walk_comments<'a, V: $visitor<'a> + ?Sized>(
+            visitor: &mut V,
+            comments: &$($mutability)? CommentsAndNewlines<'a>,
+        ) {
+            if let Some(tokens) = comments {
+                for token in tokens {
+                    visitor.$visit_token(token);
+                }
+            }
+        }
+    };
+}
+
+visitor! {
+    /// Walks a syntax tree without changing it.
+    trait Visitor;
+    /// Anything a `DelimitedLine` can hold, so `Visitor` can visit it.
+    trait Visitable;
+    ()
+    visit_ast,
+    visit_statement => walk_statement,
+    visit_expr => walk_expr,
+    visit_case => walk_case,
+    visit_variable_decl => walk_variable_decl,
+    visit_delimited_line => walk_delimited_line,
+    visit_comments => walk_comments,
+    visit_token,
+    accept,
+}
+
+visitor! {
+    /// Walks a syntax tree, with the chance to change anything in it.
+    trait VisitorMut;
+    /// Anything a `DelimitedLine` can hold, so `VisitorMut` can visit it.
+    trait VisitableMut;
+    (mut)
+    visit_ast_mut,
+    visit_statement_mut => walk_statement_mut,
+    visit_expr_mut => walk_expr_mut,
+    visit_case_mut => walk_case_mut,
+    visit_variable_decl_mut => walk_variable_decl_mut,
+    visit_delimited_line_mut => walk_delimited_line_mut,
+    visit_comments_mut => walk_comments_mut,
+    visit_token_mut,
+    accept_mut,
+}
@@ -0,0 +1,74 @@
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+
+/// Where we keep a copy of each file before overwriting it.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum Backup {
+    #[default]
+    None,
+    /// Next to the original, as `file.gml.orig`.
+    Orig,
+    /// Inside `dir`, laid out the same way the files are laid out below `root`.
+    Directory { dir: PathBuf, root: PathBuf },
+}
+
+/// Overwrites `path` with `output`, unless it already matches `original`. Returns
+/// whether we wrote anything.
+///
+/// We write to a temporary file next to `path` and then rename it over the
+/// original, so an interrupted run or a full disk never leaves a file half-written.
+pub fn write_formatted(path: &Path, original: &str, output: &str, backup: &Backup) -> io::Result<bool> {
+    if original == output {
+        return Ok(false);
+    }
+
+    match backup {
+        Backup::None => {}
+        Backup::Orig => {
+            let mut orig_path = path.as_os_str().to_owned();
+            orig_path.push(".orig");
+            fs::copy(path, orig_path)?;
+        }
+        Backup::Directory { dir, root } => {
+            let relative = match path.strip_prefix(root) {
+                Ok(relative) if relative.as_os_str().is_empty() == false => relative,
+                _ => Path::new(path.file_name().unwrap_or(path.as_os_str())),
+            };
+            let backup_path = dir.join(relative);
+            if let Some(parent) = backup_path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::copy(path, backup_path)?;
+        }
+    }
+
+    write_atomically(path, output.as_bytes())?;
+    Ok(true)
+}
+
+pub(crate) fn write_atomically(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let file_name = path.file_name().unwrap_or(path.as_os_str()).to_string_lossy();
+    let temp_path = path.with_file_name(format!(".{}.{}.gml_fmt_tmp", file_name, process::id()));
+
+    let result = write_temp_file(path, &temp_path, contents).and_then(|()| fs::rename(&temp_path, path));
+    if result.is_err() {
+        let _ = fs::remove_file(&temp_path);
+    }
+
+    result
+}
+
+fn write_temp_file(path: &Path, temp_path: &Path, contents: &[u8]) -> io::Result<()> {
+    let mut temp_file = OpenOptions::new().write(true).create_new(true).open(temp_path)?;
+    temp_file.write_all(contents)?;
+    temp_file.sync_all()?;
+
+    // The rename replaces the original, so the new file has to carry its permissions.
+    if let Ok(metadata) = fs::metadata(path) {
+        fs::set_permissions(temp_path, metadata.permissions())?;
+    }
+
+    Ok(())
+}
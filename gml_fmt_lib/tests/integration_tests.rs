@@ -7,7 +7,21 @@ const LANG_CONFIG: LangConfig = LangConfig {
 };
 
 fn run_test(input: &str) -> String {
-    run(input, &LANG_CONFIG, None).expect("Panicked during Integration Test!")
+    let output = run(input, &LANG_CONFIG, None).expect("Panicked during Integration Test!");
+    if let Err(diagnostic) = verify_tokens(input, &output) {
+        panic!("{}", diagnostic.render(input, None));
+    }
+    if let Some(diff) = idempotency_diff(input, &LANG_CONFIG).expect("Panicked during Integration Test!") {
+        panic!("Formatting a second time changed the output:\n{}", diff);
+    }
+    output
+}
+
+fn first_parse_error(input: &str) -> Diagnostic {
+    match run(input, &LANG_CONFIG, None) {
+        Err(Error::Parse { diagnostics, .. }) => diagnostics[0].clone(),
+        other => panic!("Integration Test should have failed to parse, but gave {:?}", other),
+    }
 }
 
 #[test]
@@ -506,3 +520,1035 @@ audio_stop_sound(aMusicTitle);
 
     assert_eq!(run_test(input), output);
 }
+
+#[test]
+fn line_range_formats_only_overlapping_statements() {
+    let input = "x=1;
+if (x) {
+    y=2;
+}
+z  =  3;
+";
+    let output = "x=1;
+if (x) {
+    y = 2;
+}
+z  =  3;
+";
+    let formatted = run_lines(input, &LANG_CONFIG, &[2..=2]).expect("Panicked during Integration Test!");
+    assert_eq!(formatted, output);
+}
+
+#[test]
+fn line_range_keeps_statements_sharing_a_line_together() {
+    let input = "a=1;
+b=2;   c =3;
+
+d  = 4;
+";
+    let output = "a=1;
+b = 2; c = 3;
+
+d  = 4;
+";
+    let formatted = run_lines(input, &LANG_CONFIG, &[1..=1]).expect("Panicked during Integration Test!");
+    assert_eq!(formatted, output);
+}
+
+#[test]
+fn parse_error_points_at_token() {
+    let input = "var a = 1;
+switch (a) {
+    x = 2;
+}
+";
+    let diagnostic = first_parse_error(input);
+
+    assert_eq!((diagnostic.line, diagnostic.column), (3, 5));
+    assert_eq!(LineIndex::new(input).text(&diagnostic.span), "x");
+    assert_eq!(
+        diagnostic.render(input, None),
+        "error: expected `case` or `default`, found `x`
+ --> 3:5
+  |
+3 |     x = 2;
+  |     ^
+  = help: everything inside a `switch` has to be under a `case` or `default` label
+"
+    );
+}
+
+#[test]
+fn parse_error_at_end_of_file() {
+    let input = "x = (a +\n\n";
+    let diagnostic = first_parse_error(input);
+
+    assert_eq!(diagnostic.message, "unexpected end of file");
+    assert_eq!((diagnostic.line, diagnostic.column), (1, 9));
+}
+
+#[test]
+fn recovering_keeps_broken_code_and_formats_the_rest() {
+    let input = "var  a=1;
+switch (a) {
+    x = 2;
+}
+if (a) {
+  c =   4;
+  switch (c) { y; }
+  d=5
+}
+";
+    let output = "var a = 1;
+switch (a) {
+    x = 2;
+}
+if (a) {
+    c = 4;
+    switch (c) { y; }
+    d = 5;
+}
+";
+    let formatted = run_recovering(input, &LANG_CONFIG, None);
+
+    assert_eq!(formatted.output, output);
+    assert_eq!(formatted.diagnostics.len(), 2);
+    assert_eq!(formatted.diagnostics[1].line, 7);
+}
+
+#[test]
+fn recovering_skips_the_whole_of_a_broken_multiline_statement() {
+    let input = "var  a=1;
+x = (1 +
+    2 +
+    )
+y=2;
+if (a) {
+  c=3
+  d = (4 *
+      5 *
+  )
+  // six
+  e=6
+}
+";
+    // The broken statements are left exactly as they were, lines and all.
+    let output = "var a = 1;
+x = (1 +
+    2 +
+    )
+y = 2;
+if (a) {
+    c = 3;
+    d = (4 *
+      5 *
+  )
+    // six
+    e = 6;
+}
+";
+    let formatted = run_recovering(input, &LANG_CONFIG, None);
+
+    assert_eq!(formatted.output, output);
+    assert_eq!(formatted.diagnostics.len(), 2);
+}
+
+#[test]
+fn verification_points_at_first_changed_token() {
+    let input = "switch (q) {\n    case 1:\n        break;\n}\n";
+    let output = "switch (q) {\n    case 1:\n}\n";
+
+    assert!(verify_tokens(input, "switch(q){case 1:break}").is_ok());
+
+    let diagnostic = verify_tokens(input, output).unwrap_err();
+    assert_eq!(diagnostic.message, "formatting changed `break` into `}`");
+    assert_eq!((diagnostic.line, diagnostic.column), (3, 9));
+}
+
+#[test]
+fn spans_locate_tokens_after_multiline_strings_and_tabs() {
+    let input = "var s = @\"a\n\tb\";\n\tswitch (a) { x = 2; }\n";
+
+    let diagnostic = first_parse_error(input);
+    assert_eq!((diagnostic.line, diagnostic.column), (3, 15));
+
+    let line_index = LineIndex::new(input);
+    assert_eq!(line_index.text(&diagnostic.span), "x");
+    let offset = diagnostic.span.start;
+    assert_eq!(line_index.line_col(offset), (2, 14));
+    assert_eq!(line_index.lines(&(0..offset)), (0, 2));
+    assert_eq!(line_index.text(&(offset..offset + 1)), "x");
+}
+
+#[test]
+fn statements_split_off_a_var_get_their_own_span() {
+    let input = "var a = 1, show_debug_message(a);\nb = 2;\n";
+    let ast = parse(input).unwrap();
+    let line_index = LineIndex::new(input);
+
+    let spans: Vec<_> = ast
+        .statements
+        .iter()
+        .map(|stmt| line_index.text(&stmt.span))
+        .filter(|text| !text.trim().is_empty())
+        .collect();
+    assert_eq!(spans, ["var a = 1,", "show_debug_message(a);", "b = 2;"]);
+}
+
+#[test]
+fn parse_and_format_ast_match_run() {
+    let input = "// one
+var x = 1;
+if (x) {
+    show_debug_message(x);
+}
+";
+
+    let ast = parse(input).unwrap();
+    let condition = ast
+        .statements
+        .iter()
+        .find_map(|stmt| match &stmt.statement {
+            ast::Statement::If { condition, .. } => Some(condition),
+            _ => None,
+        })
+        .unwrap();
+    assert_eq!(LineIndex::new(ast.source()).text(&condition.span), "(x)");
+
+    assert_eq!(format_ast(&ast, &LANG_CONFIG), run_test(input));
+}
+
+#[test]
+fn parse_reports_every_problem() {
+    let input = "switch (a) { x = 2; }
+switch (b) { y = 3; }
+";
+
+    let diagnostics = parse(input).unwrap_err();
+    assert_eq!(diagnostics.len(), 2);
+    assert_eq!((diagnostics[1].line, diagnostics[1].column), (2, 14));
+}
+
+/// Collects the locals a file declares, the functions it calls and how many comments it has.
+#[derive(Default)]
+struct Symbols<'a> {
+    locals: Vec<&'a str>,
+    calls: Vec<&'a str>,
+    comments: usize,
+}
+
+fn identifier<'a>(expr: &ast::Expr<'a>) -> Option<&'a str> {
+    match expr {
+        ast::Expr::Identifier { name, .. } => match name.token_type {
+            ast::TokenType::Identifier(name) => Some(name),
+            _ => None,
+        },
+        ast::Expr::Assign { left, .. } => identifier(&left.expr),
+        _ => None,
+    }
+}
+
+impl<'a> visit::Visitor<'a> for Symbols<'a> {
+    fn visit_variable_decl(&mut self, variable_decl: &ast::VariableDecl<'a>) {
+        self.locals.extend(identifier(&variable_decl.var_expr.expr));
+        visit::walk_variable_decl(self, variable_decl);
+    }
+
+    fn visit_expr(&mut self, expr: &ast::ExprBoxInterior<'a>) {
+        if let ast::Expr::Call { procedure_name, .. } = &expr.expr {
+            self.calls.extend(identifier(&procedure_name.expr));
+        }
+        visit::walk_expr(self, expr);
+    }
+
+    fn visit_token(&mut self, token: &ast::Token<'a>) {
+        if let ast::TokenType::Comment(_) | ast::TokenType::MultilineComment(_) = token.token_type {
+            self.comments += 1;
+        }
+    }
+}
+
+#[test]
+fn visitor_collects_symbols() {
+    let input = "var a = 1, b; // two locals
+for (var i = 0; i < a; i++) {
+    show_debug_message(string(i)); /* nested */
+}
+switch (b) {
+    case 1:
+        var c = max(a, b);
+        break;
+}
+";
+
+    let ast = parse(input).unwrap();
+    let mut symbols = Symbols::default();
+    visit::Visitor::visit_ast(&mut symbols, &ast);
+
+    assert_eq!(symbols.locals, ["a", "b", "i", "c"]);
+    assert_eq!(symbols.calls, ["show_debug_message", "string", "max"]);
+    assert_eq!(symbols.comments, 2);
+}
+
+struct Rename<'a> {
+    from: &'a str,
+    to: &'a str,
+}
+
+impl<'a> visit::VisitorMut<'a> for Rename<'a> {
+    fn visit_token_mut(&mut self, token: &mut ast::Token<'a>) {
+        if token.token_type == ast::TokenType::Identifier(self.from) {
+            token.token_type = ast::TokenType::Identifier(self.to);
+        }
+    }
+}
+
+#[test]
+fn visitor_mut_renames_identifiers() {
+    let input = "var count = 0;
+with (obj_enemy) {
+    other.count += hp[count];
+}
+";
+    let format = "var total = 0;
+with (obj_enemy) {
+    other.total += hp[total];
+}
+";
+
+    let mut ast = parse(input).unwrap();
+    visit::VisitorMut::visit_ast_mut(
+        &mut Rename {
+            from: "count",
+            to: "total",
+        },
+        &mut ast,
+    );
+
+    assert_eq!(format_ast(&ast, &LANG_CONFIG), format);
+}
+
+#[test]
+fn cst_round_trips_every_byte() {
+    let inputs = [
+        "",
+        "  \t\r\n",
+        "\t var x = 1 ;  \r\n\r\n\n  if (x)\t{ show_debug_message(\"a\r\nb\"); }   \n",
+        "/* one\n   two */ x = .5;// trailing   \n#region   stuff\n@\"multi\r\nline\"   \t",
+        "a = @'unterminated\n\n",
+    ];
+
+    for input in inputs.iter() {
+        assert_eq!(&Cst::new(input).to_string(), input);
+    }
+}
+
+#[test]
+fn cst_keeps_indentation_as_the_trivia_after_a_newline() {
+    let input = "if (x) {\n\t  y = 1;\n}\n";
+    let cst = Cst::new(input);
+
+    let newline = &cst.tokens()[5];
+    assert_eq!(newline.token.token_type, ast::TokenType::Newline(1));
+    assert_eq!((newline.text, newline.trailing_trivia), ("\n", "\t  "));
+    assert_eq!(
+        format_ast(&parse(input).unwrap(), &LANG_CONFIG),
+        "if (x) {\n    y = 1;\n}\n"
+    );
+}
+
+#[test]
+fn cst_replaces_tokens_and_keeps_everything_else() {
+    let input = "var  count=0;\r\n\tcount  +=  1 ;   // keep me   \r\n";
+    let cst = Cst::new(input);
+
+    let renames: Vec<_> = cst
+        .tokens()
+        .iter()
+        .enumerate()
+        .filter(|(_, t)| t.token.token_type == ast::TokenType::Identifier("count"))
+        .map(|(i, _)| (i, "total"))
+        .collect();
+
+    assert_eq!(cst.token_at(5), Some(renames[0].0));
+    assert_eq!(
+        cst.replace_tokens(renames),
+        "var  total=0;\r\n\ttotal  +=  1 ;   // keep me   \r\n"
+    );
+}
+
+#[test]
+fn errors_say_what_went_wrong() {
+    let input = "switch (a) { x = 2; }\nif (b) { switch (c) { y; } }\n";
+    match run(input, &LANG_CONFIG, None) {
+        Err(Error::Parse {
+            path: None,
+            diagnostics,
+        }) => assert_eq!(diagnostics.len(), 2),
+        other => panic!("expected a parse error, got {:?}", other),
+    }
+
+    let missing = std::path::PathBuf::from("no/such/directory");
+    match Config::new(missing.clone(), PrintFlags::empty(), false)
+        .map(drop)
+        .map_err(Error::from)
+    {
+        Err(Error::Config(ConfigError::PathNotFound(path))) => assert_eq!(path, missing),
+        other => panic!("expected a config error, got {:?}", other),
+    }
+}
+
+/// A directory under the system temp directory, removed when the test ends.
+struct TempDir(std::path::PathBuf);
+
+impl TempDir {
+    fn new(name: &str) -> TempDir {
+        let path = std::env::temp_dir().join(format!("gml_fmt_{}_{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&path);
+        std::fs::create_dir_all(&path).unwrap();
+        TempDir(path)
+    }
+
+    fn path(&self) -> std::path::PathBuf {
+        self.0.clone()
+    }
+
+    /// Writes `contents` to `relative`, making any directories it needs.
+    fn write(&self, relative: &str, contents: &str) -> std::path::PathBuf {
+        let path = self.0.join(relative);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, contents).unwrap();
+        path
+    }
+
+    fn read(&self, relative: &str) -> String {
+        std::fs::read_to_string(self.0.join(relative)).unwrap()
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+#[derive(Default)]
+struct Recorder {
+    events: Vec<String>,
+}
+
+impl Reporter for Recorder {
+    fn on_file_start(&mut self, path: &std::path::Path, _source: &str) {
+        self.events
+            .push(format!("start {}", path.file_name().unwrap().to_string_lossy()));
+    }
+
+    fn on_formatted(&mut self, path: &std::path::Path, _source: &str, formatted: &Formatted, _ast: Option<&str>) {
+        self.events.push(format!(
+            "formatted {} with {} problem(s)",
+            path.file_name().unwrap().to_string_lossy(),
+            formatted.diagnostics.len()
+        ));
+    }
+
+    fn on_error(&mut self, path: &std::path::Path, _source: Option<&str>, error: &Error) {
+        let kind = match error {
+            Error::Io { .. } => "io",
+            Error::Parse { .. } => "parse",
+            Error::Verification { .. } => "verification",
+            _ => "other",
+        };
+        self.events.push(format!(
+            "{} error {}",
+            kind,
+            path.file_name().unwrap().to_string_lossy()
+        ));
+    }
+
+    fn on_skipped(&mut self, path: &std::path::Path, reason: SkipReason) {
+        self.events.push(format!(
+            "skipped {} {:?}",
+            path.file_name().unwrap().to_string_lossy(),
+            reason
+        ));
+    }
+}
+
+#[test]
+fn reporter_hears_about_every_file_in_order() {
+    let project = std::env::temp_dir().join(format!("gml_fmt_reporter_test_{}", std::process::id()));
+    std::fs::create_dir_all(&project).unwrap();
+    std::fs::write(project.join("a.gml"), "x=1;\n").unwrap();
+    std::fs::write(project.join("b.gml"), "// @gml_fmt ignore\nx=1;\n").unwrap();
+    std::fs::write(project.join("c.gml"), "switch (a) { x = 2; }\n").unwrap();
+
+    let mut config = Config::new(project.clone(), PrintFlags::empty(), false).unwrap();
+    config.files.sort();
+    config.jobs = 3;
+
+    let mut recorder = Recorder::default();
+    let result = run_with_config(&config, &LANG_CONFIG, &mut recorder);
+    std::fs::remove_dir_all(&project).unwrap();
+
+    assert!(result.is_ok());
+    assert_eq!(
+        recorder.events,
+        [
+            "start a.gml",
+            "formatted a.gml with 0 problem(s)",
+            "skipped b.gml Ignored",
+            "start c.gml",
+            "formatted c.gml with 1 problem(s)",
+        ]
+    );
+}
+
+#[test]
+fn yyp_projects_format_only_the_code_they_reference() {
+    let project = std::env::temp_dir().join(format!("gml_fmt_yyp_test_{}", std::process::id()));
+    let write = |path: &str, contents: &str| {
+        let path = project.join(path);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, contents).unwrap();
+    };
+
+    // GameMaker 2.3 leaves a comma after the last item of everything.
+    write(
+        "game.yyp",
+        r#"{
+  "resources": [
+    {"id":{"name":"scr_move","path":"scripts/scr_move/scr_move.yy",},"order":0,},
+    {"id":{"name":"obj_player","path":"objects/obj_player/obj_player.yy",},"order":1,},
+    {"id":{"name":"rm_start","path":"rooms/rm_start/rm_start.yy",},"order":2,},
+  ],
+  "name": "game, the sequel",
+}"#,
+    );
+    write("scripts/scr_move/scr_move.yy", r#"{"name":"scr_move",}"#);
+    write("scripts/scr_move/scr_move.gml", "x=1;\n");
+    write("scripts/scr_deleted/scr_deleted.gml", "x=1;\n");
+    write(
+        "objects/obj_player/obj_player.yy",
+        r#"{"eventList":[
+            {"eventNum":0,"eventType":3,"collisionObjectId":null,},
+            {"eventNum":0,"eventType":4,"collisionObjectId":{"name":"obj_wall","path":"objects/obj_wall/obj_wall.yy",},},
+            {"eventNum":64,"eventType":8,"collisionObjectId":null,},
+        ],}"#,
+    );
+    write("objects/obj_player/Step_0.gml", "x=1;\n");
+    write("objects/obj_player/Collision_obj_wall.gml", "x=1;\n");
+    write(
+        "rooms/rm_start/rm_start.yy",
+        r#"{"creationCodeFile":"${project_dir}/rooms/rm_start/RoomCreationCode.gml","layers":[
+            {"layers":[{"instances":[{"name":"inst_1","hasCreationCode":true,},{"name":"inst_2","hasCreationCode":false,},],},],},
+        ],}"#,
+    );
+    write("rooms/rm_start/RoomCreationCode.gml", "x=1;\n");
+    write("rooms/rm_start/InstanceCreationCode_inst_1.gml", "x=1;\n");
+
+    let config = Config::new(project.clone(), PrintFlags::empty(), false).unwrap();
+    let names: Vec<_> = config
+        .files
+        .iter()
+        .map(|path| config.resource_name(path).unwrap())
+        .collect();
+
+    let filters = FileFilters {
+        only: vec![ResourceKind::Object],
+        ..FileFilters::default()
+    };
+    let only_objects = Config::with_filters(project.clone(), PrintFlags::empty(), false, filters).unwrap();
+    let report = format_files(&only_objects, &LANG_CONFIG, &mut Silent);
+    std::fs::remove_dir_all(&project).unwrap();
+
+    assert!(config.traversal_errors.is_empty());
+    assert_eq!(
+        names,
+        [
+            "scr_move",
+            "obj_player: Step",
+            "obj_player: Collision with obj_wall",
+            "rm_start: Creation Code",
+            "rm_start: inst_1 Creation Code",
+        ]
+    );
+    assert_eq!(
+        report
+            .files
+            .iter()
+            .map(|file| file.resource.as_deref().unwrap())
+            .collect::<Vec<_>>(),
+        ["obj_player: Step", "obj_player: Collision with obj_wall"]
+    );
+}
+
+#[test]
+fn gmx_code_is_formatted_in_place() {
+    let object = r#"<?xml version="1.0" encoding="utf-8"?>
+<object>
+  <events>
+    <event eventtype="3" enumb="0">
+      <action>
+        <libid>1</libid>
+        <id>603</id>
+        <kind>7</kind>
+        <arguments>
+          <argument>
+            <kind>1</kind>
+            <string>if (a&lt;b &amp;&amp; c) {
+x=1;
+}</string>
+          </argument>
+        </arguments>
+      </action>
+      <action>
+        <libid>1</libid>
+        <id>601</id>
+        <arguments>
+          <argument>
+            <string>x=1</string>
+          </argument>
+        </arguments>
+      </action>
+      <action>
+        <libid>1</libid>
+        <id>603</id>
+        <arguments>
+          <argument>
+            <string>// @gml_fmt ignore
+x=1</string>
+          </argument>
+        </arguments>
+      </action>
+    </event>
+  </events>
+</object>
+"#;
+    let room = r#"<room>
+  <code>x=1;
+</code>
+  <instances>
+    <instance objName="obj_a" x="0" y="0" name="inst_1" code="s=&quot;a&quot;;&#xA;if(b){&#xA;c=1;&#xA;}" scaleX="1"/>
+    <instance objName="obj_a" x="0" y="0" name="inst_2" code="" scaleX="1"/>
+  </instances>
+</room>
+"#;
+
+    assert_eq!(
+        format_gmx(object, &LANG_CONFIG, true).unwrap(),
+        object.replace(
+            "if (a&lt;b &amp;&amp; c) {\nx=1;\n}",
+            "if (a &lt; b &amp;&amp; c) {\n    x = 1;\n}"
+        )
+    );
+    assert_eq!(
+        format_gmx(room, &LANG_CONFIG, true).unwrap(),
+        room.replace("x=1;\n", "x = 1;\n").replace(
+            "s=&quot;a&quot;;&#xA;if(b){&#xA;c=1;&#xA;}",
+            "s = &quot;a&quot;;&#xA;if (b) {&#xA;    c = 1;&#xA;}"
+        )
+    );
+
+    let broken = "<object>\n  <action><libid>1</libid><id>603</id>\n<arguments><string>x = (1 &amp;\n</string></arguments></action>\n</object>\n";
+    match format_gmx(broken, &LANG_CONFIG, true) {
+        Err(Error::Parse { diagnostics, .. }) => assert_eq!(diagnostics[0].line, 3),
+        other => panic!("expected a parse error, got {:?}", other),
+    }
+    assert!(is_gmx_file(std::path::Path::new("objects/obj_player.object.gmx")));
+    assert!(!is_gmx_file(std::path::Path::new("game.project.gmx")));
+}
+
+#[test]
+fn worker_pool_keeps_order_and_collects_every_error() {
+    let project = TempDir::new("worker_pool_test");
+    let mut config = Config::new(project.path(), PrintFlags::OVERWRITE, false).unwrap();
+    let mut expected_events = Vec::new();
+    let mut expected_failures = Vec::new();
+
+    // Every fifth file is missing, so several workers fail while the rest carry on.
+    for i in 0..40 {
+        let name = format!("file_{:02}.gml", i);
+        let path = if i % 5 == 3 {
+            expected_events.push(format!("io error {}", name));
+            expected_failures.push(project.path().join(&name));
+            project.path().join(&name)
+        } else {
+            expected_events.push(format!("start {}", name));
+            expected_events.push(format!("formatted {} with 0 problem(s)", name));
+            project.write(&name, &format!("x={};\n", i))
+        };
+        config.files.push(path);
+    }
+    config.jobs = 4;
+
+    let mut recorder = Recorder::default();
+    let result = run_with_config(&config, &LANG_CONFIG, &mut recorder);
+    let report = format_files(&config, &LANG_CONFIG, &mut Silent);
+
+    assert_eq!(recorder.events, expected_events);
+    match result {
+        Err(Error::Files(errors)) => assert_eq!(
+            errors
+                .iter()
+                .map(|e| e.path().unwrap().to_path_buf())
+                .collect::<Vec<_>>(),
+            expected_failures
+        ),
+        other => panic!("expected every missing file to fail, got {:?}", other),
+    }
+    assert_eq!(
+        report.files.iter().map(|file| &file.path).collect::<Vec<_>>(),
+        config.files.iter().collect::<Vec<_>>()
+    );
+    assert_eq!(report.totals.failed, 8);
+    assert_eq!(project.read("file_00.gml"), "x = 0;\n");
+    assert_eq!(project.read("file_39.gml"), "x = 39;\n");
+}
+
+/// The files `filters` takes in under `root`, relative to it and sorted.
+fn filtered_files(root: &std::path::Path, filters: FileFilters) -> Vec<String> {
+    let config = Config::with_filters(root.to_path_buf(), PrintFlags::empty(), false, filters).unwrap();
+    let mut files: Vec<_> = config
+        .files
+        .iter()
+        .map(|path| {
+            let relative = path.strip_prefix(root).unwrap();
+            relative.to_string_lossy().replace('\\', "/")
+        })
+        .collect();
+    files.sort();
+    files
+}
+
+#[test]
+fn file_filters_narrow_what_we_walk() {
+    let project = TempDir::new("filters_test");
+    for file in [
+        "a.gml",
+        "sub/b.gml",
+        "generated/c.gml",
+        "vendor/d.gml",
+        "notes/e.gml",
+        "notes/keep.gml",
+        "build/f.gml",
+        "readme.txt",
+    ] {
+        project.write(file, "x=1;\n");
+    }
+    project.write(".gml_fmt_ignore", "vendor/\n");
+    project.write("notes/.gml_fmt_ignore", "e.gml\n");
+    project.write(".gitignore", "build/\n");
+    let root = project.path();
+
+    // `.gml_fmt_ignore` files always apply, wherever they are.
+    assert_eq!(
+        filtered_files(&root, FileFilters::default()),
+        ["a.gml", "build/f.gml", "generated/c.gml", "notes/keep.gml", "sub/b.gml"]
+    );
+
+    let exclude = FileFilters {
+        exclude: vec!["generated/".to_owned(), "a.gml".to_owned()],
+        ..FileFilters::default()
+    };
+    assert_eq!(
+        filtered_files(&root, exclude),
+        ["build/f.gml", "notes/keep.gml", "sub/b.gml"]
+    );
+
+    let include = FileFilters {
+        include: vec!["sub/**".to_owned(), "notes/**".to_owned()],
+        ..FileFilters::default()
+    };
+    assert_eq!(filtered_files(&root, include), ["notes/keep.gml", "sub/b.gml"]);
+
+    let gitignore = FileFilters {
+        respect_gitignore: true,
+        ..FileFilters::default()
+    };
+    assert_eq!(
+        filtered_files(&root, gitignore),
+        ["a.gml", "generated/c.gml", "notes/keep.gml", "sub/b.gml"]
+    );
+
+    // A file has to pass every filter: excludes win over includes, and nothing
+    // brings back what an ignore file skipped.
+    let combined = FileFilters {
+        include: vec!["**/*.gml".to_owned()],
+        exclude: vec!["sub/".to_owned()],
+        respect_gitignore: true,
+        ..FileFilters::default()
+    };
+    assert_eq!(
+        filtered_files(&root, combined),
+        ["a.gml", "generated/c.gml", "notes/keep.gml"]
+    );
+}
+
+#[test]
+fn file_filters_read_from_gml_fmt_toml() {
+    let project = TempDir::new("filters_toml_test");
+    project.write("a.gml", "x=1;\n");
+    project.write("skip/b.gml", "x=1;\n");
+    project.write("gml_fmt.toml", "exclude = [\"skip/\"]\nrespect_gitignore = true\n");
+
+    let filters = FileFilters::load(&project.path()).unwrap();
+    assert_eq!(filters.exclude, ["skip/"]);
+    assert!(filters.respect_gitignore);
+    assert_eq!(filtered_files(&project.path(), filters), ["a.gml"]);
+}
+
+#[test]
+fn ignore_files_apply_to_yyp_projects() {
+    let project = TempDir::new("filters_yyp_test");
+    let mut resources = String::new();
+    for name in ["scr_a", "scr_generated", "scr_build", "scr_kept"] {
+        resources.push_str(&format!(
+            r#"{{"id":{{"name":"{0}","path":"scripts/{0}/{0}.yy"}}}},"#,
+            name
+        ));
+        project.write(
+            &format!("scripts/{0}/{0}.yy", name),
+            &format!(r#"{{"name":"{}"}}"#, name),
+        );
+        project.write(&format!("scripts/{0}/{0}.gml", name), "x=1;\n");
+    }
+    project.write("game.yyp", &format!(r#"{{"resources":[{}]}}"#, resources));
+    project.write(".gml_fmt_ignore", "scripts/scr_generated/\n");
+    project.write("scripts/.gitignore", "scr_*.gml\n");
+    project.write("scripts/scr_kept/.gml_fmt_ignore", "!scr_kept.gml\n");
+
+    assert_eq!(
+        filtered_files(&project.path(), FileFilters::default()),
+        [
+            "scripts/scr_a/scr_a.gml",
+            "scripts/scr_build/scr_build.gml",
+            "scripts/scr_kept/scr_kept.gml"
+        ]
+    );
+
+    let gitignore = FileFilters {
+        respect_gitignore: true,
+        ..FileFilters::default()
+    };
+    assert_eq!(
+        filtered_files(&project.path(), gitignore),
+        ["scripts/scr_kept/scr_kept.gml"]
+    );
+}
+
+#[test]
+fn take_in_file_checks_one_new_path_against_the_filters() {
+    let project = TempDir::new("take_in_file_test");
+    project.write("a.gml", "x=1;\n");
+    project.write(".gml_fmt_ignore", "ignored/\n");
+    let filters = FileFilters {
+        exclude: vec!["generated/".to_owned()],
+        ..FileFilters::default()
+    };
+    let mut config = Config::with_filters(project.path(), PrintFlags::empty(), false, filters).unwrap();
+    assert_eq!(config.files, [project.path().join("a.gml")]);
+
+    let new = project.write("sub/new.gml", "x=1;\n");
+    let generated = project.write("generated/b.gml", "x=1;\n");
+    let ignored = project.write("ignored/c.gml", "x=1;\n");
+    let text = project.write("notes.txt", "x=1;\n");
+
+    // A path spelled differently, as a file watcher might, comes back in the config's spelling.
+    let canonical = std::fs::canonicalize(project.path().join("a.gml")).unwrap();
+    assert_eq!(
+        config.take_in_file(&canonical).unwrap(),
+        Some(project.path().join("a.gml"))
+    );
+    assert_eq!(config.take_in_file(&new).unwrap(), Some(new.clone()));
+    assert_eq!(config.take_in_file(&generated).unwrap(), None);
+    assert_eq!(config.take_in_file(&ignored).unwrap(), None);
+    assert_eq!(config.take_in_file(&text).unwrap(), None);
+    assert_eq!(config.files, [project.path().join("a.gml"), new]);
+}
+
+#[cfg(unix)]
+#[test]
+fn walking_skips_broken_symlinks_and_loops() {
+    use std::os::unix::fs::symlink;
+
+    let project = TempDir::new("symlink_test");
+    project.write("a.gml", "x=1;\n");
+    project.write("sub/b.gml", "x=1;\n");
+    symlink(project.path().join("nowhere.gml"), project.path().join("broken.gml")).unwrap();
+    symlink(project.path(), project.path().join("sub/loop")).unwrap();
+
+    let filters = FileFilters {
+        follow_symlinks: true,
+        ..FileFilters::default()
+    };
+    let config = Config::with_filters(project.path(), PrintFlags::empty(), false, filters).unwrap();
+
+    let mut files = config.files.clone();
+    files.sort();
+    assert_eq!(files, [project.path().join("a.gml"), project.path().join("sub/b.gml")]);
+
+    assert_eq!(config.traversal_errors.len(), 2, "{:?}", config.traversal_errors);
+    assert!(config.traversal_errors.iter().any(|e| matches!(
+        e,
+        TraversalError::BrokenSymlink(path) if path.ends_with("broken.gml")
+    )));
+    assert!(config.traversal_errors.iter().any(|e| matches!(
+        e,
+        TraversalError::SymlinkLoop { path, .. } if path.ends_with("sub/loop")
+    )));
+}
+
+#[cfg(unix)]
+#[test]
+fn walking_skips_unreadable_directories() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let project = TempDir::new("unreadable_test");
+    project.write("a.gml", "x=1;\n");
+    project.write("locked/b.gml", "x=1;\n");
+    let locked = project.path().join("locked");
+    std::fs::set_permissions(&locked, std::fs::Permissions::from_mode(0o000)).unwrap();
+
+    // Permissions don't stop root, so there's nothing to test there.
+    let can_read_anyway = std::fs::read_dir(&locked).is_ok();
+    let config = Config::new(project.path(), PrintFlags::empty(), false);
+    std::fs::set_permissions(&locked, std::fs::Permissions::from_mode(0o755)).unwrap();
+    if can_read_anyway {
+        return;
+    }
+
+    let config = config.unwrap();
+    assert_eq!(config.files, [project.path().join("a.gml")]);
+    assert!(matches!(
+        config.traversal_errors.as_slice(),
+        [TraversalError::Unreadable { path, .. }] if path == &locked
+    ));
+}
+
+#[test]
+fn config_files_in_unreadable_directories_are_errors() {
+    let missing = std::path::PathBuf::from("no/such/directory");
+    assert!(matches!(
+        LangConfig::load(&missing),
+        Err(ConfigError::Unreadable { path, .. }) if path == missing
+    ));
+    assert!(FileFilters::load(&missing).is_err());
+}
+
+#[test]
+fn orig_backups_keep_the_unformatted_file() {
+    let project = TempDir::new("orig_test");
+    let path = project.write("a.gml", "x=1;\n");
+
+    assert!(write_formatted(&path, "x=1;\n", "x = 1;\n", &Backup::Orig).unwrap());
+    assert_eq!(project.read("a.gml"), "x = 1;\n");
+    assert_eq!(project.read("a.gml.orig"), "x=1;\n");
+
+    // Nothing changes, so the backup from before is left alone.
+    assert!(!write_formatted(&path, "x = 1;\n", "x = 1;\n", &Backup::Orig).unwrap());
+    assert_eq!(project.read("a.gml.orig"), "x=1;\n");
+
+    let mut names: Vec<_> = std::fs::read_dir(project.path())
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+        .collect();
+    names.sort();
+    assert_eq!(names, ["a.gml", "a.gml.orig"]);
+}
+
+#[test]
+fn directory_backups_mirror_the_project_layout() {
+    let project = TempDir::new("backup_dir_test");
+    let top = project.write("proj/a.gml", "x=1;\n");
+    let nested = project.write("proj/scripts/b/b.gml", "y=2;\n");
+    let outside = project.write("elsewhere/c.gml", "z=3;\n");
+    let backup = Backup::Directory {
+        dir: project.path().join("backup"),
+        root: project.path().join("proj"),
+    };
+
+    write_formatted(&top, "x=1;\n", "x = 1;\n", &backup).unwrap();
+    write_formatted(&nested, "y=2;\n", "y = 2;\n", &backup).unwrap();
+    // A file outside the root has nowhere to mirror, so it goes at the top.
+    write_formatted(&outside, "z=3;\n", "z = 3;\n", &backup).unwrap();
+
+    assert_eq!(project.read("backup/a.gml"), "x=1;\n");
+    assert_eq!(project.read("backup/scripts/b/b.gml"), "y=2;\n");
+    assert_eq!(project.read("backup/c.gml"), "z=3;\n");
+    assert_eq!(project.read("proj/scripts/b/b.gml"), "y = 2;\n");
+}
+
+#[cfg(unix)]
+#[test]
+fn writing_keeps_the_file_mode() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let project = TempDir::new("mode_test");
+    let path = project.write("a.gml", "x=1;\n");
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o640)).unwrap();
+
+    write_formatted(&path, "x=1;\n", "x = 1;\n", &Backup::None).unwrap();
+
+    assert_eq!(project.read("a.gml"), "x = 1;\n");
+    let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+    assert_eq!(mode & 0o777, 0o640);
+}
+
+/// Formats `project` with its cache turned on, and gives back what the reporter heard.
+fn cached_run(project: &TempDir, lang_config: &LangConfig) -> Vec<String> {
+    let mut config = Config::new(project.path(), PrintFlags::OVERWRITE, false).unwrap();
+    config.files.sort();
+    config.cache = Some(project.path().join(CACHE_FILE_NAME));
+
+    let mut recorder = Recorder::default();
+    run_with_config(&config, lang_config, &mut recorder).unwrap();
+    recorder.events
+}
+
+#[test]
+fn cache_is_thrown_out_when_the_config_or_version_changes() {
+    let project = TempDir::new("cache_test");
+    project.write("a.gml", "if (x) {\ny=1;\n}\n");
+
+    assert_eq!(
+        cached_run(&project, &LANG_CONFIG),
+        ["start a.gml", "formatted a.gml with 0 problem(s)"]
+    );
+    assert_eq!(cached_run(&project, &LANG_CONFIG), ["skipped a.gml Cached"]);
+
+    // A different indent in gml_fmt.toml means the file is no longer formatted.
+    project.write("gml_fmt.toml", "space_size = 2\n");
+    let two_spaces = LangConfig::load(&project.path()).unwrap();
+    assert_eq!(
+        cached_run(&project, &two_spaces),
+        ["start a.gml", "formatted a.gml with 0 problem(s)"]
+    );
+    assert_eq!(project.read("a.gml"), "if (x) {\n  y = 1;\n}\n");
+    assert_eq!(cached_run(&project, &two_spaces), ["skipped a.gml Cached"]);
+
+    // A cache written by another version of gml_fmt is ignored.
+    let cache = project.read(CACHE_FILE_NAME);
+    let version = format!("version = \"{}\"", env!("CARGO_PKG_VERSION"));
+    assert!(cache.contains(&version), "{}", cache);
+    project.write(CACHE_FILE_NAME, &cache.replace(&version, "version = \"0.0.0\""));
+    assert_eq!(
+        cached_run(&project, &two_spaces),
+        ["start a.gml", "formatted a.gml with 0 problem(s)"]
+    );
+    assert_eq!(cached_run(&project, &two_spaces), ["skipped a.gml Cached"]);
+}
+
+#[test]
+fn cache_forgets_files_which_are_gone() {
+    let project = TempDir::new("cache_prune_test");
+    project.write("a.gml", "x=1;\n");
+    let b = project.write("b.gml", "y=2;\n");
+    cached_run(&project, &LANG_CONFIG);
+    assert!(project.read(CACHE_FILE_NAME).contains("b.gml"));
+
+    std::fs::remove_file(b).unwrap();
+    assert_eq!(cached_run(&project, &LANG_CONFIG), ["skipped a.gml Cached"]);
+    let cache = project.read(CACHE_FILE_NAME);
+    assert!(cache.contains("a.gml"));
+    assert!(!cache.contains("b.gml"), "{}", cache);
+}